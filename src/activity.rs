@@ -0,0 +1,88 @@
+use serde::{Deserialize, Serialize};
+use solana_transaction_status::option_serializer::OptionSerializer;
+use solana_transaction_status::{
+    EncodedConfirmedTransactionWithStatusMeta, EncodedTransaction, UiInstruction, UiMessage,
+    UiParsedInstruction,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ActivityKind {
+    Transfer,
+    Mint,
+    Burn,
+    SetAuthority,
+    /// Any other token-program instruction referencing the mint, by its
+    /// parsed instruction type (e.g. `initializeAccount3`).
+    Other(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MintActivity {
+    pub signature: String,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub kind: ActivityKind,
+    /// Raw token amount moved, when the instruction carries one.
+    pub amount: Option<String>,
+    pub failed: bool,
+}
+
+fn classify_kind(instruction_type: &str) -> ActivityKind {
+    match instruction_type {
+        "transfer" | "transferChecked" | "transferCheckedWithFee" => ActivityKind::Transfer,
+        "mintTo" | "mintToChecked" => ActivityKind::Mint,
+        "burn" | "burnChecked" => ActivityKind::Burn,
+        "setAuthority" => ActivityKind::SetAuthority,
+        other => ActivityKind::Other(other.to_string()),
+    }
+}
+
+/// Extracts one `MintActivity` per token-program instruction, top-level or
+/// inner, whose parsed `info.mint` is `mint`. Expects a transaction fetched
+/// with `jsonParsed` encoding.
+pub fn classify_transaction(
+    signature: &str,
+    mint: &str,
+    transaction: &EncodedConfirmedTransactionWithStatusMeta,
+) -> Vec<MintActivity> {
+    let mut instructions: Vec<&UiInstruction> = Vec::new();
+    if let EncodedTransaction::Json(ui_transaction) = &transaction.transaction.transaction {
+        if let UiMessage::Parsed(message) = &ui_transaction.message {
+            instructions.extend(message.instructions.iter());
+        }
+    }
+
+    let meta = transaction.transaction.meta.as_ref();
+    if let Some(OptionSerializer::Some(inner)) = meta.map(|meta| &meta.inner_instructions) {
+        instructions.extend(inner.iter().flat_map(|inner| inner.instructions.iter()));
+    }
+    let failed = meta.is_some_and(|meta| meta.err.is_some());
+
+    instructions
+        .into_iter()
+        .filter_map(|instruction| match instruction {
+            UiInstruction::Parsed(UiParsedInstruction::Parsed(parsed))
+                if parsed.program == "spl-token" || parsed.program == "spl-token-2022" =>
+            {
+                Some(&parsed.parsed)
+            }
+            _ => None,
+        })
+        .filter(|parsed| parsed["info"]["mint"] == mint)
+        .map(|parsed| {
+            let info = &parsed["info"];
+            let amount = info["amount"]
+                .as_str()
+                .or_else(|| info["tokenAmount"]["amount"].as_str())
+                .map(|amount| amount.to_string());
+            MintActivity {
+                signature: signature.to_string(),
+                slot: transaction.slot,
+                block_time: transaction.block_time,
+                kind: classify_kind(parsed["type"].as_str().unwrap_or_default()),
+                amount,
+                failed,
+            }
+        })
+        .collect()
+}
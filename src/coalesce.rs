@@ -0,0 +1,131 @@
+use serde_json::Value;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{oneshot, Semaphore};
+
+/// Most ids Jupiter accepts in one price request.
+pub const PRICE_IDS_LIMIT: usize = 100;
+
+/// `None` when the response had no entry for the mint.
+type EntryResult = Result<Option<Value>, ChunkError>;
+
+/// Failure of a coalesced price request, handed to every mint of its chunk.
+/// `source` is the upstream error, so timeouts can still be told apart.
+#[derive(Debug, Clone)]
+pub struct ChunkError(Arc<dyn Error + Send + Sync>);
+
+impl fmt::Display for ChunkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Price batch failed: {}", self.0)
+    }
+}
+
+impl Error for ChunkError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&*self.0)
+    }
+}
+
+/// Collects single-mint price lookups arriving within `window` and serves
+/// them with one `ids=a,b,c` request. The first caller of a window spawns the
+/// flush, so dropping any caller cannot strand the others.
+pub struct PriceCoalescer {
+    http_client: reqwest::Client,
+    permits: Arc<Semaphore>,
+    base_url: String,
+    window: Duration,
+    pending: Mutex<Vec<(String, oneshot::Sender<EntryResult>)>>,
+}
+
+impl PriceCoalescer {
+    pub fn new(
+        http_client: reqwest::Client,
+        permits: Arc<Semaphore>,
+        base_url: &str,
+        window: Duration,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            http_client,
+            permits,
+            base_url: base_url.to_string(),
+            window,
+            pending: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// The mint's entry from the `data` map of the batched response.
+    pub async fn fetch_entry(
+        self: &Arc<Self>,
+        mint: &str,
+    ) -> Result<Option<Value>, Box<dyn Error>> {
+        let (sender, receiver) = oneshot::channel();
+        let starts_window = {
+            let mut pending = self.pending.lock().unwrap();
+            pending.push((mint.to_string(), sender));
+            pending.len() == 1
+        };
+
+        if starts_window {
+            let coalescer = Arc::clone(self);
+            tokio::spawn(async move {
+                tokio::time::sleep(coalescer.window).await;
+                coalescer.flush().await;
+            });
+        }
+
+        Ok(receiver.await.map_err(|_| "Price batch was dropped")??)
+    }
+
+    async fn flush(&self) {
+        let waiters = std::mem::take(&mut *self.pending.lock().unwrap());
+        let mut mints: Vec<&str> = waiters.iter().map(|(mint, _)| mint.as_str()).collect();
+        mints.sort_unstable();
+        mints.dedup();
+
+        let mut entries = HashMap::new();
+        let mut failed = HashMap::new();
+        for chunk in mints.chunks(PRICE_IDS_LIMIT) {
+            match fetch_price_chunk(&self.http_client, &self.permits, &self.base_url, chunk).await {
+                Ok(chunk_entries) => entries.extend(chunk_entries),
+                Err(e) => {
+                    let error = ChunkError(Arc::from(e));
+                    for &mint in chunk {
+                        failed.insert(mint.to_string(), error.clone());
+                    }
+                }
+            }
+        }
+
+        for (mint, sender) in waiters {
+            let result = match entries.get(&mint) {
+                Some(entry) => Ok(Some(entry.clone())),
+                None => match failed.get(&mint) {
+                    Some(e) => Err(e.clone()),
+                    None => Ok(None),
+                },
+            };
+            let _ = sender.send(result);
+        }
+    }
+}
+
+/// Fetches up to `PRICE_IDS_LIMIT` mints in one request, once one of
+/// `permits` is free, returning the response's `data` entries keyed by mint.
+pub async fn fetch_price_chunk(
+    http_client: &reqwest::Client,
+    permits: &Semaphore,
+    base_url: &str,
+    mints: &[&str],
+) -> Result<HashMap<String, Value>, Box<dyn Error + Send + Sync>> {
+    let _permit = permits.acquire().await?;
+    let url = format!("{}?ids={}", base_url, mints.join(","));
+    let mut data: Value = http_client.get(&url).send().await?.json().await?;
+    let entries = match data["data"].take() {
+        Value::Object(entries) => entries.into_iter().collect(),
+        _ => HashMap::new(),
+    };
+    Ok(entries)
+}
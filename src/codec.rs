@@ -0,0 +1,193 @@
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Nonce};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde_json::Value;
+
+/// Env var consulted for the cache encryption key when the config has none.
+pub const ENCRYPTION_KEY_ENV: &str = "CACHE_ENCRYPTION_KEY";
+
+/// Transforms serialized cache rows on their way to and from Postgres.
+///
+/// Rows written by the pass-through codec (version 0) are stored as plain
+/// JSONB, so existing rows and SQL that reaches into the JSON keep working.
+/// Any other codec's output is stored as a base64 JSON string of
+/// `[version byte][payload]`, which lets the read path pick the right codec
+/// per row. JSON-path SQL (`metadata->>'name'`) yields NULL on such rows, so
+/// reads must select the whole column and pass it through `open`.
+pub trait CacheCodec: Send + Sync {
+    fn version(&self) -> u8;
+    fn encode(&self, plain: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>>;
+    fn decode(&self, encoded: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>>;
+}
+
+pub struct PlainCodec;
+
+impl CacheCodec for PlainCodec {
+    fn version(&self) -> u8 {
+        0
+    }
+
+    fn encode(&self, plain: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        Ok(plain.to_vec())
+    }
+
+    fn decode(&self, encoded: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        Ok(encoded.to_vec())
+    }
+}
+
+/// AES-256-GCM with a random 96-bit nonce prepended to each ciphertext.
+pub struct AesGcmCodec {
+    cipher: Aes256Gcm,
+}
+
+impl AesGcmCodec {
+    const NONCE_LEN: usize = 12;
+
+    /// Builds the codec from a base64-encoded 32-byte key.
+    pub fn from_base64_key(key: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let key = BASE64.decode(key.trim())?;
+        let cipher = Aes256Gcm::new_from_slice(&key)
+            .map_err(|_| format!("Cache encryption key must be 32 bytes, got {}", key.len()))?;
+        Ok(Self { cipher })
+    }
+}
+
+impl CacheCodec for AesGcmCodec {
+    fn version(&self) -> u8 {
+        1
+    }
+
+    fn encode(&self, plain: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, plain)
+            .map_err(|_| "Failed to encrypt cache row")?;
+        let mut encoded = nonce.to_vec();
+        encoded.extend(ciphertext);
+        Ok(encoded)
+    }
+
+    fn decode(&self, encoded: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        if encoded.len() < Self::NONCE_LEN {
+            return Err("Encrypted cache row is truncated".into());
+        }
+        let (nonce, ciphertext) = encoded.split_at(Self::NONCE_LEN);
+        Ok(self
+            .cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| "Failed to decrypt cache row (wrong key?)")?)
+    }
+}
+
+/// The codec new rows are written with, plus every codec rows may be read
+/// with.
+pub struct CacheCodecs {
+    active: Box<dyn CacheCodec>,
+    readers: Vec<Box<dyn CacheCodec>>,
+}
+
+impl CacheCodecs {
+    pub fn new(active: Box<dyn CacheCodec>) -> Self {
+        Self {
+            active,
+            readers: vec![Box::new(PlainCodec)],
+        }
+    }
+
+    /// Uses AES-GCM when a key is given (or found in `CACHE_ENCRYPTION_KEY`),
+    /// pass-through otherwise.
+    pub fn from_key(key: Option<&str>) -> Result<Self, Box<dyn std::error::Error>> {
+        let env_key = std::env::var(ENCRYPTION_KEY_ENV).ok();
+        match key.or(env_key.as_deref()) {
+            Some(key) => Ok(Self::new(Box::new(AesGcmCodec::from_base64_key(key)?))),
+            None => Ok(Self::new(Box::new(PlainCodec))),
+        }
+    }
+
+    pub fn seal(&self, value: Value) -> Result<Value, Box<dyn std::error::Error>> {
+        if self.active.version() == 0 {
+            return Ok(value);
+        }
+        let mut sealed = vec![self.active.version()];
+        sealed.extend(self.active.encode(&serde_json::to_vec(&value)?)?);
+        Ok(Value::String(BASE64.encode(sealed)))
+    }
+
+    pub fn open(&self, stored: Value) -> Result<Value, Box<dyn std::error::Error>> {
+        let Value::String(encoded) = stored else {
+            return Ok(stored);
+        };
+        let sealed = BASE64.decode(encoded)?;
+        let (&version, payload) = sealed.split_first().ok_or("Empty encoded cache row")?;
+        let codec = std::iter::once(&self.active)
+            .chain(&self.readers)
+            .find(|codec| codec.version() == version)
+            .ok_or_else(|| format!("Cache row uses unknown codec version {}", version))?;
+        Ok(serde_json::from_slice(&codec.decode(payload)?)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn aes_codecs(key_byte: u8) -> CacheCodecs {
+        let key = BASE64.encode([key_byte; 32]);
+        CacheCodecs::new(Box::new(AesGcmCodec::from_base64_key(&key).unwrap()))
+    }
+
+    #[test]
+    fn plain_rows_stay_json() {
+        let codecs = CacheCodecs::new(Box::new(PlainCodec));
+        let row = json!({"name": "Bonk", "decimals": 5});
+        let sealed = codecs.seal(row.clone()).unwrap();
+        assert_eq!(sealed, row);
+        assert_eq!(codecs.open(sealed).unwrap(), row);
+    }
+
+    #[test]
+    fn aes_round_trip_with_version_byte() {
+        let codecs = aes_codecs(7);
+        let row = json!({"name": "Bonk", "symbol": "BONK"});
+        let sealed = codecs.seal(row.clone()).unwrap();
+
+        let Value::String(encoded) = &sealed else {
+            panic!("sealed row is not a string: {}", sealed);
+        };
+        let bytes = BASE64.decode(encoded).unwrap();
+        assert_eq!(bytes[0], 1);
+        assert_eq!(
+            bytes.len(),
+            1 + AesGcmCodec::NONCE_LEN + 16 + row.to_string().len()
+        );
+        assert_eq!(codecs.open(sealed).unwrap(), row);
+    }
+
+    #[test]
+    fn aes_codecs_still_read_plain_rows() {
+        let row = json!({"name": "Bonk"});
+        assert_eq!(aes_codecs(7).open(row.clone()).unwrap(), row);
+    }
+
+    #[test]
+    fn wrong_key_fails_to_open() {
+        let sealed = aes_codecs(7).seal(json!({"name": "Bonk"})).unwrap();
+        assert!(aes_codecs(8).open(sealed).is_err());
+    }
+
+    #[test]
+    fn unknown_version_is_rejected() {
+        let stored = Value::String(BASE64.encode([9u8, 1, 2, 3]));
+        let error = aes_codecs(7).open(stored).unwrap_err();
+        assert!(error.to_string().contains("unknown codec version 9"));
+    }
+
+    #[test]
+    fn short_keys_are_rejected() {
+        assert!(AesGcmCodec::from_base64_key(&BASE64.encode([1u8; 16])).is_err());
+    }
+}
@@ -0,0 +1,184 @@
+use crate::listener::ListenerError;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_request::RpcRequest;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Fields of a DAS `getAsset` result for a compressed NFT, which has no mint
+/// or metadata account to read on-chain.
+#[derive(Debug, Clone)]
+pub struct CompressedAsset {
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub update_authority: String,
+    pub seller_fee_basis_points: u16,
+    pub is_mutable: bool,
+    /// `None` when the asset lists no creators.
+    pub creators_verified: Option<bool>,
+}
+
+impl CompressedAsset {
+    /// Parses a `getAsset` result, returning `None` unless the asset is
+    /// marked compressed.
+    pub fn from_asset(asset: &Value) -> Option<Self> {
+        if asset["compression"]["compressed"] != true {
+            return None;
+        }
+
+        let metadata = &asset["content"]["metadata"];
+        let text = |value: &Value| value.as_str().unwrap_or_default().to_string();
+        let creators = asset["creators"].as_array().filter(|c| !c.is_empty());
+
+        Some(Self {
+            name: text(&metadata["name"]),
+            symbol: text(&metadata["symbol"]),
+            uri: text(&asset["content"]["json_uri"]),
+            update_authority: asset["authorities"]
+                .as_array()
+                .and_then(|authorities| authorities.first())
+                .map(|authority| text(&authority["address"]))
+                .unwrap_or_default(),
+            seller_fee_basis_points: asset["royalty"]["basis_points"]
+                .as_u64()
+                .unwrap_or_default() as u16,
+            is_mutable: asset["mutable"].as_bool().unwrap_or_default(),
+            creators_verified: creators
+                .map(|creators| creators.iter().any(|creator| creator["verified"] == true)),
+        })
+    }
+}
+
+/// Page size cap of `getAssetsByGroup`.
+const ASSETS_PAGE_LIMIT: usize = 1000;
+
+/// A change to a collection's membership or to one member's metadata.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CollectionUpdate {
+    Added {
+        mint: String,
+    },
+    Removed {
+        mint: String,
+    },
+    /// Name, symbol, URI, creators, royalty, authorities or mutability
+    /// changed.
+    Changed {
+        mint: String,
+    },
+}
+
+/// Every member of the verified collection `collection`, through the DAS
+/// `getAssetsByGroup` method, mapped to a fingerprint of its metadata. Only
+/// fingerprints are kept so large collections stay cheap to diff. Pages go
+/// through `rpc_client`, so they count against its concurrency and rate
+/// limits like any other RPC call.
+pub async fn collection_members(
+    rpc_client: &RpcClient,
+    collection: &str,
+) -> Result<HashMap<String, u64>, ListenerError> {
+    let mut members = HashMap::new();
+    for page in 1.. {
+        let params = serde_json::json!({
+            "groupKey": "collection",
+            "groupValue": collection,
+            "page": page,
+            "limit": ASSETS_PAGE_LIMIT,
+        });
+        let result: Value = rpc_client
+            .send(
+                RpcRequest::Custom {
+                    method: "getAssetsByGroup",
+                },
+                params,
+            )
+            .await?;
+
+        let items = result["items"]
+            .as_array()
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        for asset in items {
+            if let Some(id) = asset["id"].as_str() {
+                members.insert(id.to_string(), fingerprint(asset));
+            }
+        }
+        if items.len() < ASSETS_PAGE_LIMIT {
+            break;
+        }
+    }
+    Ok(members)
+}
+
+/// Hash of the metadata fields of a DAS asset that `Changed` reports on.
+fn fingerprint(asset: &Value) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for field in [
+        &asset["content"]["metadata"],
+        &asset["content"]["json_uri"],
+        &asset["creators"],
+        &asset["royalty"],
+        &asset["authorities"],
+        &asset["mutable"],
+    ] {
+        field.to_string().hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// Updates between two `collection_members` snapshots.
+fn diff_members(old: &HashMap<String, u64>, new: &HashMap<String, u64>) -> Vec<CollectionUpdate> {
+    let mut updates = Vec::new();
+    for (mint, fingerprint) in new {
+        match old.get(mint) {
+            None => updates.push(CollectionUpdate::Added { mint: mint.clone() }),
+            Some(previous) if previous != fingerprint => {
+                updates.push(CollectionUpdate::Changed { mint: mint.clone() })
+            }
+            Some(_) => {}
+        }
+    }
+    for mint in old.keys().filter(|mint| !new.contains_key(*mint)) {
+        updates.push(CollectionUpdate::Removed { mint: mint.clone() });
+    }
+    updates
+}
+
+/// Re-enumerates `collection` every `interval`, emitting what changed since
+/// the previous pass until the receiver is dropped. Members already present
+/// on the first pass are not reported as added; a failed pass is skipped and
+/// the next one diffs against the last good snapshot.
+pub async fn run_collection_watch(
+    rpc_client: RpcClient,
+    collection: String,
+    interval: Duration,
+    sender: mpsc::Sender<CollectionUpdate>,
+) {
+    let mut ticker = tokio::time::interval(interval);
+    let mut members: Option<HashMap<String, u64>> = None;
+
+    while !sender.is_closed() {
+        ticker.tick().await;
+        let current = match collection_members(&rpc_client, &collection).await {
+            Ok(current) => current,
+            Err(e) => {
+                eprintln!("Collection watch for {} failed: {}", collection, e);
+                continue;
+            }
+        };
+
+        if let Some(previous) = &members {
+            for update in diff_members(previous, &current) {
+                if sender.send(update).await.is_err() {
+                    return;
+                }
+            }
+        }
+        members = Some(current);
+    }
+}
@@ -0,0 +1,620 @@
+use crate::listener::{EndpointPool, ListenerError, LogEvent, RECONNECT_DELAY};
+use crate::programs;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::nonblocking::pubsub_client::PubsubClient;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use solana_client::rpc_filter::{Memcmp, RpcFilterType};
+use solana_sdk::account::Account;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::hash::hash;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio_postgres::Client;
+
+/// Offset of the `user` field, right after the Anchor discriminator.
+const USER_OFFSET: usize = 8;
+/// Bytes up to and including `out_received`.
+const MIN_ORDER_LEN: usize = 160;
+/// Bytes of a `FilledEvent` after its discriminator: user, order, input and
+/// output mint, in and out amount, fee mint and fee.
+const FILLED_EVENT_LEN: usize = 32 * 5 + 8 * 3;
+/// How often the full order list is re-read. Closed accounts no longer match
+/// the `user` filter, so the subscription itself never reports them.
+const RESYNC_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DcaOrder {
+    pub address: String,
+    pub user: String,
+    pub input_mint: String,
+    pub output_mint: String,
+    /// Raw input amounts.
+    pub in_deposited: u64,
+    pub in_used: u64,
+    /// Raw output amount received so far.
+    pub out_received: u64,
+}
+
+impl DcaOrder {
+    pub fn decode(address: &str, data: &[u8]) -> Option<Self> {
+        if data.len() < MIN_ORDER_LEN {
+            return None;
+        }
+        let pubkey = |offset: usize| Pubkey::try_from(&data[offset..offset + 32]).ok();
+        let amount = |offset: usize| {
+            data[offset..offset + 8]
+                .try_into()
+                .ok()
+                .map(u64::from_le_bytes)
+        };
+
+        Some(Self {
+            address: address.to_string(),
+            user: pubkey(USER_OFFSET)?.to_string(),
+            input_mint: pubkey(40)?.to_string(),
+            output_mint: pubkey(72)?.to_string(),
+            in_deposited: amount(120)?,
+            in_used: amount(144)?,
+            out_received: amount(152)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum DcaOrderEvent {
+    Opened(DcaOrder),
+    /// One or more cycles filled since the last observation.
+    Filled {
+        order: DcaOrder,
+        in_amount: u64,
+        out_amount: u64,
+    },
+    Closed {
+        address: String,
+    },
+}
+
+/// Last known state of one wallet's orders, turning account updates into
+/// events. Accounts of any other user are ignored.
+pub struct UserOrders {
+    user: String,
+    orders: HashMap<String, DcaOrder>,
+}
+
+impl UserOrders {
+    pub fn new(user: &Pubkey) -> Self {
+        Self {
+            user: user.to_string(),
+            orders: HashMap::new(),
+        }
+    }
+
+    /// Applies the new data of one order account; empty or undecodable data
+    /// means the account was closed.
+    pub fn apply(&mut self, address: &str, data: &[u8]) -> Option<DcaOrderEvent> {
+        let Some(order) = DcaOrder::decode(address, data).filter(|order| order.user == self.user)
+        else {
+            return self.orders.remove(address).map(|_| DcaOrderEvent::Closed {
+                address: address.to_string(),
+            });
+        };
+
+        let event = match self.orders.get(address) {
+            None => Some(DcaOrderEvent::Opened(order.clone())),
+            Some(previous)
+                if order.in_used > previous.in_used
+                    || order.out_received > previous.out_received =>
+            {
+                Some(DcaOrderEvent::Filled {
+                    order: order.clone(),
+                    in_amount: order.in_used.saturating_sub(previous.in_used),
+                    out_amount: order.out_received.saturating_sub(previous.out_received),
+                })
+            }
+            Some(_) => None,
+        };
+        self.orders.insert(address.to_string(), order);
+        event
+    }
+
+    /// Replaces the known orders with a full listing, returning the events
+    /// that explain the difference.
+    pub fn reconcile(&mut self, accounts: &[(Pubkey, Account)]) -> Vec<DcaOrderEvent> {
+        let listed: HashMap<String, &[u8]> = accounts
+            .iter()
+            .map(|(address, account)| (address.to_string(), account.data.as_slice()))
+            .collect();
+
+        let mut events: Vec<DcaOrderEvent> = listed
+            .iter()
+            .filter_map(|(address, data)| self.apply(address, data))
+            .collect();
+        let closed: Vec<String> = self
+            .orders
+            .keys()
+            .filter(|address| !listed.contains_key(*address))
+            .cloned()
+            .collect();
+        events.extend(closed.iter().filter_map(|address| self.apply(address, &[])));
+        events
+    }
+}
+
+fn user_filter(user: &Pubkey) -> RpcProgramAccountsConfig {
+    RpcProgramAccountsConfig {
+        filters: Some(vec![RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+            USER_OFFSET,
+            user.as_ref(),
+        ))]),
+        account_config: RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            commitment: Some(CommitmentConfig::confirmed()),
+            ..RpcAccountInfoConfig::default()
+        },
+        ..RpcProgramAccountsConfig::default()
+    }
+}
+
+/// Streams open/fill/close events for `user`'s DCA orders into `sender` until
+/// the receiver is dropped. Orders that already exist when the watch starts
+/// are taken as the baseline and not reported as opened.
+pub async fn run_user_dca_subscription(
+    endpoints: Arc<EndpointPool>,
+    rpc_client: RpcClient,
+    user: Pubkey,
+    sender: mpsc::Sender<DcaOrderEvent>,
+) {
+    let mut orders = UserOrders::new(&user);
+    let mut baseline_taken = false;
+
+    while !sender.is_closed() {
+        let (index, websocket_url) = endpoints.current();
+        let result = stream_orders(
+            &endpoints,
+            index,
+            &websocket_url,
+            &rpc_client,
+            &user,
+            &mut orders,
+            &mut baseline_taken,
+            &sender,
+        )
+        .await;
+        if sender.is_closed() {
+            break;
+        }
+        match result {
+            Ok(()) => {
+                eprintln!("DCA subscription for {} closed, reconnecting", user);
+                endpoints.record_failure(index, "subscription closed".to_string());
+            }
+            Err(e) => {
+                eprintln!("DCA subscription for {} failed: {}", user, e);
+                endpoints.record_failure(index, e.to_string());
+            }
+        }
+
+        if !sender.is_closed() {
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn stream_orders(
+    endpoints: &EndpointPool,
+    index: usize,
+    websocket_url: &str,
+    rpc_client: &RpcClient,
+    user: &Pubkey,
+    orders: &mut UserOrders,
+    baseline_taken: &mut bool,
+    sender: &mpsc::Sender<DcaOrderEvent>,
+) -> Result<(), ListenerError> {
+    let program = programs::JUPITER_DCA;
+    let client = PubsubClient::new(websocket_url).await?;
+    let (mut stream, unsubscribe) = client
+        .program_subscribe(&program, Some(user_filter(user)))
+        .await?;
+    endpoints.record_success(index);
+
+    // Subscribed first so nothing lands between the listing and the stream;
+    // the listing also catches up on whatever changed while disconnected.
+    let accounts = rpc_client
+        .get_program_accounts_with_config(&program, user_filter(user))
+        .await?;
+    let mut events = orders.reconcile(&accounts);
+    if !*baseline_taken {
+        *baseline_taken = true;
+        events.clear();
+    }
+
+    let start = tokio::time::Instant::now() + RESYNC_INTERVAL;
+    let mut resync = tokio::time::interval_at(start, RESYNC_INTERVAL);
+    loop {
+        for event in events.drain(..) {
+            if sender.send(event).await.is_err() {
+                drop(stream);
+                unsubscribe().await;
+                return Ok(());
+            }
+        }
+
+        events = tokio::select! {
+            response = stream.next() => {
+                let Some(response) = response else {
+                    break;
+                };
+                let keyed = response.value;
+                let data = keyed.account.decode::<Account>().map(|account| account.data);
+                orders
+                    .apply(&keyed.pubkey, data.as_deref().unwrap_or_default())
+                    .into_iter()
+                    .collect()
+            }
+            _ = resync.tick() => {
+                let accounts = rpc_client
+                    .get_program_accounts_with_config(&program, user_filter(user))
+                    .await?;
+                orders.reconcile(&accounts)
+            }
+        };
+    }
+
+    drop(stream);
+    unsubscribe().await;
+    Ok(())
+}
+
+/// One cycle fill, decoded from the `FilledEvent` the DCA program emits.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DcaFill {
+    pub user: String,
+    pub order: String,
+    pub input_mint: String,
+    pub output_mint: String,
+    /// Raw amounts swapped in this fill.
+    pub in_amount: u64,
+    pub out_amount: u64,
+    pub fee_mint: String,
+    pub fee: u64,
+}
+
+impl DcaFill {
+    /// The first `FilledEvent` among the transaction's `Program data:` logs.
+    pub fn from_logs(logs: &[String]) -> Option<Self> {
+        let discriminator = &hash(b"event:FilledEvent").to_bytes()[..8];
+        logs.iter()
+            .filter_map(|log| log.strip_prefix("Program data: "))
+            .filter_map(|data| BASE64.decode(data).ok())
+            .find_map(|data| {
+                let event = data.strip_prefix(discriminator)?;
+                Self::decode(event)
+            })
+    }
+
+    fn decode(data: &[u8]) -> Option<Self> {
+        if data.len() < FILLED_EVENT_LEN {
+            return None;
+        }
+        let pubkey = |offset: usize| Pubkey::try_from(&data[offset..offset + 32]).ok();
+        let amount = |offset: usize| {
+            data[offset..offset + 8]
+                .try_into()
+                .ok()
+                .map(u64::from_le_bytes)
+        };
+
+        Some(Self {
+            user: pubkey(0)?.to_string(),
+            order: pubkey(32)?.to_string(),
+            input_mint: pubkey(64)?.to_string(),
+            output_mint: pubkey(96)?.to_string(),
+            in_amount: amount(128)?,
+            out_amount: amount(136)?,
+            fee_mint: pubkey(144)?.to_string(),
+            fee: amount(176)?,
+        })
+    }
+}
+
+/// What a reconciliation expects a fill to look like.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DcaFillExpectation {
+    pub order: String,
+    pub input_mint: String,
+    pub output_mint: String,
+    pub in_amount: u64,
+    pub out_amount: u64,
+    /// Allowed deviation of either amount, in basis points of the expected
+    /// value.
+    pub tolerance_bps: u32,
+}
+
+impl DcaFillExpectation {
+    pub fn matches(&self, fill: &DcaFill) -> bool {
+        let within = |actual: u64, expected: u64| {
+            let allowed = expected as u128 * self.tolerance_bps as u128 / 10_000;
+            (actual as u128).abs_diff(expected as u128) <= allowed
+        };
+        fill.order == self.order
+            && fill.input_mint == self.input_mint
+            && fill.output_mint == self.output_mint
+            && within(fill.in_amount, self.in_amount)
+            && within(fill.out_amount, self.out_amount)
+    }
+}
+
+/// What a DCA program transaction did, read from its instruction logs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DcaEventKind {
+    Open,
+    Fill,
+    Deposit,
+    Withdraw,
+    Close,
+    Other,
+}
+
+impl DcaEventKind {
+    /// Classifies by the first `Instruction: <name>` log written after the
+    /// DCA program is invoked.
+    pub fn from_logs(logs: &[String]) -> Self {
+        let invoke = format!("Program {} invoke", programs::JUPITER_DCA);
+        let instruction = logs
+            .iter()
+            .skip_while(|log| !log.starts_with(&invoke))
+            .find_map(|log| log.strip_prefix("Program log: Instruction: "));
+        match instruction {
+            Some("OpenDca" | "OpenDcaV2") => Self::Open,
+            Some("Fulfill" | "FulfillFlashFill" | "FulfillDlmmFill") => Self::Fill,
+            Some("Deposit") => Self::Deposit,
+            Some("Withdraw" | "WithdrawFees") => Self::Withdraw,
+            Some("CloseDca" | "EndAndClose") => Self::Close,
+            _ => Self::Other,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Open => "open",
+            Self::Fill => "fill",
+            Self::Deposit => "deposit",
+            Self::Withdraw => "withdraw",
+            Self::Close => "close",
+            Self::Other => "other",
+        }
+    }
+
+    fn from_column(kind: &str) -> Self {
+        match kind {
+            "open" => Self::Open,
+            "fill" => Self::Fill,
+            "deposit" => Self::Deposit,
+            "withdraw" => Self::Withdraw,
+            "close" => Self::Close,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// A DCA program transaction as recorded in `dca_events`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DcaEvent {
+    pub signature: String,
+    pub slot: u64,
+    pub kind: DcaEventKind,
+    pub failed: bool,
+    pub logs: Vec<String>,
+}
+
+impl From<LogEvent> for DcaEvent {
+    fn from(event: LogEvent) -> Self {
+        Self {
+            signature: event.signature,
+            slot: event.slot,
+            kind: DcaEventKind::from_logs(&event.logs),
+            failed: event.failed,
+            logs: event.logs,
+        }
+    }
+}
+
+/// Stores the event unless its signature is already recorded. Returns
+/// whether it was new.
+pub async fn record_event(
+    db_client: &Client,
+    event: &DcaEvent,
+) -> Result<bool, tokio_postgres::Error> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() as i64)
+        .unwrap_or_default();
+    let payload = serde_json::json!({ "failed": event.failed, "logs": event.logs });
+
+    let inserted = db_client
+        .execute(
+            "INSERT INTO dca_events (signature, slot, kind, payload, recorded_at) 
+             VALUES ($1, $2, $3, $4, $5)
+             ON CONFLICT (signature) DO NOTHING",
+            &[
+                &event.signature,
+                &(event.slot as i64),
+                &event.kind.as_str(),
+                &payload,
+                &now,
+            ],
+        )
+        .await?;
+    Ok(inserted > 0)
+}
+
+/// Recorded events from `since_slot` on, in slot order.
+pub async fn replay_events(
+    db_client: &Client,
+    since_slot: i64,
+) -> Result<Vec<DcaEvent>, tokio_postgres::Error> {
+    let rows = db_client
+        .query(
+            "SELECT signature, slot, kind, payload FROM dca_events 
+             WHERE slot >= $1 ORDER BY slot, recorded_at",
+            &[&since_slot],
+        )
+        .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            let slot: i64 = row.get(1);
+            let kind: String = row.get(2);
+            let payload: Value = row.get(3);
+            DcaEvent {
+                signature: row.get(0),
+                slot: slot as u64,
+                kind: DcaEventKind::from_column(&kind),
+                failed: payload["failed"].as_bool().unwrap_or_default(),
+                logs: serde_json::from_value(payload["logs"].clone()).unwrap_or_default(),
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn order_data(user: &Pubkey, in_used: u64, out_received: u64) -> Vec<u8> {
+        let mut data = vec![0u8; MIN_ORDER_LEN];
+        data[USER_OFFSET..USER_OFFSET + 32].copy_from_slice(user.as_ref());
+        data[40..72].copy_from_slice(&[1u8; 32]);
+        data[72..104].copy_from_slice(&[2u8; 32]);
+        data[120..128].copy_from_slice(&1_000u64.to_le_bytes());
+        data[144..152].copy_from_slice(&in_used.to_le_bytes());
+        data[152..160].copy_from_slice(&out_received.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn decodes_order_fields() {
+        let user = Pubkey::new_unique();
+        let order = DcaOrder::decode("order", &order_data(&user, 100, 7)).unwrap();
+        assert_eq!(order.user, user.to_string());
+        assert_eq!(order.input_mint, Pubkey::from([1u8; 32]).to_string());
+        assert_eq!(order.output_mint, Pubkey::from([2u8; 32]).to_string());
+        assert_eq!(order.in_deposited, 1_000);
+        assert_eq!(order.in_used, 100);
+        assert_eq!(order.out_received, 7);
+    }
+
+    #[test]
+    fn short_order_data_is_rejected() {
+        let data = order_data(&Pubkey::new_unique(), 0, 0);
+        assert_eq!(DcaOrder::decode("order", &data[..MIN_ORDER_LEN - 1]), None);
+    }
+
+    #[test]
+    fn user_orders_report_open_fill_and_close() {
+        let user = Pubkey::new_unique();
+        let mut orders = UserOrders::new(&user);
+
+        let opened = orders.apply("order", &order_data(&user, 0, 0));
+        assert!(matches!(opened, Some(DcaOrderEvent::Opened(_))));
+        assert_eq!(orders.apply("order", &order_data(&user, 0, 0)), None);
+
+        match orders.apply("order", &order_data(&user, 250, 40)) {
+            Some(DcaOrderEvent::Filled {
+                in_amount,
+                out_amount,
+                ..
+            }) => assert_eq!((in_amount, out_amount), (250, 40)),
+            other => panic!("expected a fill, got {:?}", other),
+        }
+
+        assert_eq!(
+            orders.apply("order", &[]),
+            Some(DcaOrderEvent::Closed {
+                address: "order".to_string()
+            })
+        );
+        assert_eq!(orders.apply("order", &[]), None);
+    }
+
+    #[test]
+    fn other_users_orders_are_ignored() {
+        let mut orders = UserOrders::new(&Pubkey::new_unique());
+        let data = order_data(&Pubkey::new_unique(), 0, 0);
+        assert_eq!(orders.apply("order", &data), None);
+    }
+
+    fn filled_event_log(in_amount: u64, out_amount: u64) -> String {
+        let mut data = hash(b"event:FilledEvent").to_bytes()[..8].to_vec();
+        for byte in 1..=4u8 {
+            data.extend([byte; 32]);
+        }
+        data.extend(in_amount.to_le_bytes());
+        data.extend(out_amount.to_le_bytes());
+        data.extend([5u8; 32]);
+        data.extend(3u64.to_le_bytes());
+        format!("Program data: {}", BASE64.encode(data))
+    }
+
+    #[test]
+    fn decodes_filled_event_from_logs() {
+        let logs = vec![
+            "Program log: Instruction: Fulfill".to_string(),
+            "Program data: bm90IGFuIGV2ZW50".to_string(),
+            filled_event_log(500, 42),
+        ];
+        let fill = DcaFill::from_logs(&logs).unwrap();
+        assert_eq!(fill.order, Pubkey::from([2u8; 32]).to_string());
+        assert_eq!(fill.output_mint, Pubkey::from([4u8; 32]).to_string());
+        assert_eq!((fill.in_amount, fill.out_amount), (500, 42));
+        assert_eq!(fill.fee_mint, Pubkey::from([5u8; 32]).to_string());
+        assert_eq!(fill.fee, 3);
+    }
+
+    #[test]
+    fn logs_without_filled_event_have_no_fill() {
+        let logs = vec!["Program data: bm90IGFuIGV2ZW50".to_string()];
+        assert_eq!(DcaFill::from_logs(&logs), None);
+    }
+
+    #[test]
+    fn expectation_allows_tolerance() {
+        let fill = DcaFill::from_logs(&[filled_event_log(10_000, 2_000)]).unwrap();
+        let mut expected = DcaFillExpectation {
+            order: fill.order.clone(),
+            input_mint: fill.input_mint.clone(),
+            output_mint: fill.output_mint.clone(),
+            in_amount: 10_000,
+            out_amount: 2_020,
+            tolerance_bps: 100,
+        };
+        assert!(expected.matches(&fill));
+        expected.tolerance_bps = 50;
+        assert!(!expected.matches(&fill));
+    }
+
+    #[test]
+    fn classifies_instruction_after_dca_invoke() {
+        let logs = vec![
+            "Program log: Instruction: Deposit".to_string(),
+            format!("Program {} invoke [1]", programs::JUPITER_DCA),
+            "Program log: Instruction: FulfillFlashFill".to_string(),
+        ];
+        assert_eq!(DcaEventKind::from_logs(&logs), DcaEventKind::Fill);
+        assert_eq!(DcaEventKind::from_logs(&logs[..1]), DcaEventKind::Other);
+        for kind in [DcaEventKind::Open, DcaEventKind::Close, DcaEventKind::Other] {
+            assert_eq!(DcaEventKind::from_column(kind.as_str()), kind);
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use crate::sanitize::short_address;
+use solana_client::client_error::ClientError;
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Errors raised by `TokenService` itself, as opposed to failures bubbled up
+/// from RPC, Postgres or HTTP. Returned boxed; public entry points wrap
+/// failures in `Failed`, so callers that care about the specific case should
+/// go through `TokenServiceError::cause` rather than `downcast_ref` directly.
+#[derive(Debug)]
+pub enum TokenServiceError {
+    /// The price API knows about the mint but reported a null price.
+    PriceUnavailable { mint: String },
+    /// The price API response had no entry for the mint.
+    PriceMissing { mint: String },
+    /// The quote API could not route a swap into the mint.
+    NoRoute { mint: String },
+    /// The mint has no Metaplex metadata account (yet).
+    MetadataNotFound { mint: String },
+    /// The metadata account read for `expected` names a different mint.
+    MintMismatch { expected: String, actual: String },
+    /// No account exists at the address.
+    AccountNotFound { account: String },
+    /// A fresh API price for `mint` is too far from its on-chain oracle.
+    OracleDeviation {
+        mint: String,
+        price: f64,
+        oracle_price: f64,
+    },
+    /// The address is not an SPL Token or Token-2022 token account.
+    NotATokenAccount { account: String, reason: String },
+    /// A call in `phase` (`rpc`, `db` or `http`) ran into its configured
+    /// deadline of `elapsed`.
+    Timeout {
+        phase: &'static str,
+        elapsed: Duration,
+    },
+    /// The `getMultipleAccounts` batch the account was read in failed; every
+    /// account of the batch shares `source`.
+    BatchFailed { source: Arc<ClientError> },
+    /// The subsystem was turned off in the config.
+    Disabled { subsystem: &'static str },
+    /// `operation` failed for `mint`, empty for operations that are not about
+    /// one mint; `source` is the underlying error.
+    Failed {
+        operation: &'static str,
+        mint: String,
+        source: Box<dyn Error>,
+    },
+}
+
+impl TokenServiceError {
+    /// Wraps an error with the operation and mint it happened in, for use
+    /// with `map_err`.
+    pub fn context(
+        operation: &'static str,
+        mint: &str,
+    ) -> impl FnOnce(Box<dyn Error>) -> Box<dyn Error> {
+        let mint = mint.to_string();
+        move |source| {
+            Box::new(Self::Failed {
+                operation,
+                mint,
+                source,
+            })
+        }
+    }
+
+    /// The innermost `TokenServiceError` behind any `Failed` context, or
+    /// `None` when the error did not originate in `TokenService`.
+    pub fn cause<'a>(error: &'a (dyn Error + 'static)) -> Option<&'a TokenServiceError> {
+        match error.downcast_ref::<TokenServiceError>()? {
+            Self::Failed { source, .. } => Self::cause(source.as_ref()),
+            other => Some(other),
+        }
+    }
+}
+
+impl fmt::Display for TokenServiceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::PriceUnavailable { mint } => write!(f, "No price available for {}", mint),
+            Self::PriceMissing { mint } => write!(f, "Price response has no entry for {}", mint),
+            Self::NoRoute { mint } => write!(f, "No swap route found for {}", mint),
+            Self::MetadataNotFound { mint } => write!(f, "No metadata account for {}", mint),
+            Self::MintMismatch { expected, actual } => write!(
+                f,
+                "Metadata fetched for {} belongs to mint {}",
+                expected, actual
+            ),
+            Self::AccountNotFound { account } => write!(f, "No account at {}", account),
+            Self::OracleDeviation {
+                mint,
+                price,
+                oracle_price,
+            } => write!(
+                f,
+                "Price {} for {} deviates from its oracle price {}",
+                price, mint, oracle_price
+            ),
+            Self::NotATokenAccount { account, reason } => {
+                write!(f, "{} is not a token account: {}", account, reason)
+            }
+            Self::Timeout { phase, elapsed } => {
+                write!(f, "{} timed out after {:?}", phase, elapsed)
+            }
+            Self::BatchFailed { source } => write!(f, "Account batch read failed: {}", source),
+            Self::Disabled { subsystem } => write!(f, "{} is disabled", subsystem),
+            Self::Failed {
+                operation,
+                mint,
+                source,
+            } if mint.is_empty() => write!(f, "failed {}: {}", operation, source),
+            Self::Failed {
+                operation,
+                mint,
+                source,
+            } => write!(
+                f,
+                "failed {} for {}: {}",
+                operation,
+                short_address(mint),
+                source
+            ),
+        }
+    }
+}
+
+impl Error for TokenServiceError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Failed { source, .. } => Some(source.as_ref()),
+            Self::BatchFailed { source } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use crate::ratelimit::RateLimiter;
+use async_trait::async_trait;
+use solana_client::client_error::{ClientError, ClientErrorKind};
+use solana_client::nonblocking::rpc_client::RpcClient as NonblockingRpcClient;
+use solana_client::rpc_request::RpcRequest;
+use solana_rpc_client::http_sender::HttpSender;
+use solana_rpc_client::rpc_client::RpcClientConfig;
+use solana_rpc_client::rpc_sender::{RpcSender, RpcTransportStats};
+use solana_sdk::commitment_config::CommitmentConfig;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+
+/// Client for price, quote and metadata requests. An explicit `proxy` URL,
+/// which may carry `user:pass@` credentials, routes every request through it;
+/// otherwise the `HTTP_PROXY`/`HTTPS_PROXY`/`NO_PROXY` variables apply.
+/// `timeout` bounds each request unless the request sets its own.
+pub fn build_http_client(
+    proxy: Option<&str>,
+    timeout: Duration,
+) -> Result<reqwest::Client, reqwest::Error> {
+    let mut builder = reqwest::Client::builder().timeout(timeout);
+    if let Some(proxy) = proxy {
+        builder = builder.proxy(reqwest::Proxy::all(proxy)?);
+    }
+    builder.build()
+}
+
+/// Shared by every RPC client of a service: the in-flight request limit,
+/// the rate-limit budget reported by the provider and the request timeout.
+#[derive(Clone)]
+pub struct RpcLimits {
+    pub permits: Arc<Semaphore>,
+    pub rate_limiter: Arc<RateLimiter>,
+    pub timeout: Duration,
+}
+
+impl RpcLimits {
+    pub fn new(concurrency: usize, timeout: Duration) -> Self {
+        Self {
+            permits: Arc::new(Semaphore::new(concurrency.max(1))),
+            rate_limiter: Arc::new(RateLimiter::default()),
+            timeout,
+        }
+    }
+}
+
+/// JSON-RPC sender honouring the same proxy settings. `solana-rpc-client`
+/// is built on reqwest 0.11, hence the separate client.
+fn rpc_sender(
+    rpc_url: &str,
+    proxy: Option<&str>,
+    rate_limiter: Arc<RateLimiter>,
+    timeout: Duration,
+) -> Result<HttpSender, rpc_reqwest::Error> {
+    let mut builder = rpc_reqwest::Client::builder()
+        .default_headers(HttpSender::default_headers())
+        .timeout(timeout)
+        .pool_idle_timeout(timeout);
+    if let Some(proxy) = proxy {
+        builder = builder.proxy(rpc_reqwest::Proxy::all(proxy)?);
+    }
+    let client = reqwest_middleware::ClientBuilder::new(builder.build()?)
+        .with_arc(rate_limiter)
+        .build();
+    Ok(HttpSender::new_with_client_with_middleware(rpc_url, client))
+}
+
+/// Waits for one of `permits` before each request, so every client sharing
+/// the semaphore stays within one concurrency limit.
+struct LimitedSender {
+    inner: HttpSender,
+    permits: Arc<Semaphore>,
+}
+
+#[async_trait]
+impl RpcSender for LimitedSender {
+    async fn send(
+        &self,
+        request: RpcRequest,
+        params: serde_json::Value,
+    ) -> Result<serde_json::Value, ClientError> {
+        let _permit = self
+            .permits
+            .acquire()
+            .await
+            .map_err(|e| ClientError::from(ClientErrorKind::Custom(e.to_string())))?;
+        self.inner.send(request, params).await
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        self.inner.get_transport_stats()
+    }
+
+    fn url(&self) -> String {
+        self.inner.url()
+    }
+}
+
+fn limited_sender(
+    rpc_url: &str,
+    proxy: Option<&str>,
+    limits: &RpcLimits,
+) -> Result<LimitedSender, rpc_reqwest::Error> {
+    Ok(LimitedSender {
+        inner: rpc_sender(rpc_url, proxy, limits.rate_limiter.clone(), limits.timeout)?,
+        permits: limits.permits.clone(),
+    })
+}
+
+/// Client for `TokenService`'s own reads, at the default commitment. Async
+/// so that calls made from async code never block a runtime worker.
+pub fn rpc_client(
+    rpc_url: &str,
+    proxy: Option<&str>,
+    limits: &RpcLimits,
+) -> Result<NonblockingRpcClient, rpc_reqwest::Error> {
+    Ok(NonblockingRpcClient::new_sender(
+        limited_sender(rpc_url, proxy, limits)?,
+        RpcClientConfig::with_commitment(CommitmentConfig::default()),
+    ))
+}
+
+/// Async client used by the subscriptions, at `confirmed` commitment.
+pub fn nonblocking_rpc_client(
+    rpc_url: &str,
+    proxy: Option<&str>,
+    limits: &RpcLimits,
+) -> Result<NonblockingRpcClient, rpc_reqwest::Error> {
+    Ok(NonblockingRpcClient::new_sender(
+        limited_sender(rpc_url, proxy, limits)?,
+        RpcClientConfig::with_commitment(CommitmentConfig::confirmed()),
+    ))
+}
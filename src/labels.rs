@@ -0,0 +1,61 @@
+use crate::programs;
+use solana_sdk::pubkey;
+use solana_sdk::pubkey::Pubkey;
+
+/// Wallets that hold tokens on behalf of a pool or that no one controls.
+const KNOWN_OWNERS: &[(Pubkey, &str)] = &[
+    (
+        pubkey!("1nc1nerator11111111111111111111111111111111"),
+        "Burn (incinerator)",
+    ),
+    (
+        pubkey!("11111111111111111111111111111111"),
+        "Burn (system program)",
+    ),
+    (
+        pubkey!("5Q544fKrFoe6tsEbD7S8EmxGTJYAKtTVhAW5Q5pge4j1"),
+        "Raydium AMM",
+    ),
+    (
+        pubkey!("GpMZbSM2GgvTKHJirzeGfMFoaZ8UR2X7F4v8vHTvxFbL"),
+        "Raydium CPMM",
+    ),
+];
+
+/// Programs whose accounts own pool vaults, for owners that are per-pool PDAs.
+const KNOWN_OWNER_PROGRAMS: &[(Pubkey, &str)] = &[
+    (
+        pubkey!("CAMMCzo5YL8w4VFF8KVHrK22GGUsp5VTaW7grrKgrWqK"),
+        "Raydium CLMM",
+    ),
+    (
+        pubkey!("whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc"),
+        "Orca Whirlpool",
+    ),
+    (
+        pubkey!("LBUZKhRxPF3XUpBCjp4YzTKgLccjZhTSDM9YuVaPwxo"),
+        "Meteora DLMM",
+    ),
+    (
+        pubkey!("Eo7WjKq67rjJQSZxS6z3YkapzY3eMj6Xy8X5EQVn5UaB"),
+        "Meteora pool",
+    ),
+    (programs::PUMP_FUN, "Pump.fun bonding curve"),
+    (
+        pubkey!("pAMMBay6oceH9fJKBRHGP5D4bD4sWpmSwMn52FMfXEA"),
+        "PumpSwap pool",
+    ),
+];
+
+fn lookup(table: &[(Pubkey, &'static str)], key: &Pubkey) -> Option<&'static str> {
+    table
+        .iter()
+        .find(|(address, _)| address == key)
+        .map(|(_, label)| *label)
+}
+
+/// Label for a token holder, matched first on the owner wallet itself and
+/// then on the program that owns the owner account.
+pub fn holder_label(owner: &Pubkey, owner_program: Option<&Pubkey>) -> Option<&'static str> {
+    lookup(KNOWN_OWNERS, owner).or_else(|| lookup(KNOWN_OWNER_PROGRAMS, owner_program?))
+}
@@ -0,0 +1,21 @@
+pub mod activity;
+pub mod coalesce;
+pub mod codec;
+pub mod config;
+pub mod das;
+pub mod dca;
+pub mod error;
+pub mod http;
+pub mod labels;
+pub mod listener;
+pub mod metrics;
+pub mod offchain;
+pub mod oracle;
+pub mod programs;
+pub mod ratelimit;
+pub mod risk;
+pub mod sanitize;
+pub mod sink;
+pub mod stats;
+pub mod token_service;
+pub mod tradeability;
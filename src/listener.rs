@@ -0,0 +1,326 @@
+use futures_util::StreamExt;
+use serde::Serialize;
+use solana_client::nonblocking::pubsub_client::PubsubClient;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_client::GetConfirmedSignaturesForAddress2Config;
+use solana_client::rpc_config::{
+    RpcTransactionConfig, RpcTransactionLogsConfig, RpcTransactionLogsFilter,
+};
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_transaction_status::option_serializer::OptionSerializer;
+use solana_transaction_status::UiTransactionEncoding;
+use std::collections::{HashSet, VecDeque};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+pub(crate) type ListenerError = Box<dyn std::error::Error + Send + Sync>;
+
+/// How many recent signatures are remembered to dedupe live and backfilled
+/// events against each other.
+const SEEN_CAPACITY: usize = 10_000;
+pub(crate) const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone)]
+pub struct LogEvent {
+    pub signature: String,
+    pub slot: u64,
+    pub failed: bool,
+    pub logs: Vec<String>,
+    /// True when the event was recovered via `getSignaturesForAddress` after a
+    /// reconnect rather than delivered by the live subscription.
+    pub backfilled: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EndpointHealth {
+    pub url: String,
+    pub active: bool,
+    pub consecutive_failures: u32,
+    pub total_failures: u64,
+    pub last_error: Option<String>,
+}
+
+struct PoolState {
+    current: usize,
+    health: Vec<EndpointHealth>,
+}
+
+/// WebSocket endpoints shared by every subscription. After
+/// `failover_after` consecutive failures on the active endpoint, all
+/// subscriptions reconnect to the next one in the list.
+pub struct EndpointPool {
+    failover_after: u32,
+    state: Mutex<PoolState>,
+}
+
+impl EndpointPool {
+    pub fn new(endpoints: Vec<String>, failover_after: u32) -> Self {
+        let health = endpoints
+            .into_iter()
+            .enumerate()
+            .map(|(index, url)| EndpointHealth {
+                url,
+                active: index == 0,
+                consecutive_failures: 0,
+                total_failures: 0,
+                last_error: None,
+            })
+            .collect();
+        Self {
+            failover_after: failover_after.max(1),
+            state: Mutex::new(PoolState { current: 0, health }),
+        }
+    }
+
+    pub(crate) fn current(&self) -> (usize, String) {
+        let state = self.state.lock().unwrap();
+        (state.current, state.health[state.current].url.clone())
+    }
+
+    pub(crate) fn record_success(&self, index: usize) {
+        let mut state = self.state.lock().unwrap();
+        state.health[index].consecutive_failures = 0;
+    }
+
+    pub(crate) fn record_failure(&self, index: usize, error: String) {
+        let mut state = self.state.lock().unwrap();
+        let failover_after = self.failover_after;
+        let endpoint = &mut state.health[index];
+        endpoint.consecutive_failures += 1;
+        endpoint.total_failures += 1;
+        endpoint.last_error = Some(error);
+
+        // Only the active endpoint can trigger a switch; late failures from
+        // subscriptions still on an old endpoint must not skip past a new one.
+        if index == state.current
+            && state.health[index].consecutive_failures >= failover_after
+            && state.health.len() > 1
+        {
+            let next = (index + 1) % state.health.len();
+            eprintln!(
+                "WebSocket endpoint {} failed {} times, failing over to {}",
+                state.health[index].url, failover_after, state.health[next].url
+            );
+            state.health[index].active = false;
+            state.health[next].active = true;
+            state.health[next].consecutive_failures = 0;
+            state.current = next;
+        }
+    }
+
+    pub fn health(&self) -> Vec<EndpointHealth> {
+        self.state.lock().unwrap().health.clone()
+    }
+}
+
+/// Bounded set of already-emitted signatures.
+struct SeenSignatures {
+    order: VecDeque<String>,
+    set: HashSet<String>,
+}
+
+impl SeenSignatures {
+    fn new() -> Self {
+        Self {
+            order: VecDeque::with_capacity(SEEN_CAPACITY),
+            set: HashSet::with_capacity(SEEN_CAPACITY),
+        }
+    }
+
+    fn contains(&self, signature: &str) -> bool {
+        self.set.contains(signature)
+    }
+
+    /// Records the signature, returning false if it was already seen.
+    fn insert(&mut self, signature: &str) -> bool {
+        if !self.set.insert(signature.to_string()) {
+            return false;
+        }
+        self.order.push_back(signature.to_string());
+        if self.order.len() > SEEN_CAPACITY {
+            if let Some(oldest) = self.order.pop_front() {
+                self.set.remove(&oldest);
+            }
+        }
+        true
+    }
+}
+
+/// Streams logs mentioning `program` into `sender` until the receiver is
+/// dropped. Every reconnect first replays the signatures that landed since the
+/// last emitted event, giving at-least-once delivery across disconnects.
+pub async fn run_log_subscription(
+    endpoints: Arc<EndpointPool>,
+    rpc_client: RpcClient,
+    program: Pubkey,
+    sender: mpsc::Sender<LogEvent>,
+) {
+    let mut seen = SeenSignatures::new();
+    let mut last_signature: Option<String> = None;
+
+    while !sender.is_closed() {
+        if let Some(until) = last_signature.clone() {
+            if let Err(e) = backfill(
+                &rpc_client,
+                &program,
+                &until,
+                &mut seen,
+                &mut last_signature,
+                &sender,
+            )
+            .await
+            {
+                eprintln!("Log backfill for {} failed: {}", program, e);
+            }
+        }
+
+        let (index, websocket_url) = endpoints.current();
+        let result = stream_logs(
+            &endpoints,
+            index,
+            &websocket_url,
+            &program,
+            &mut seen,
+            &mut last_signature,
+            &sender,
+        )
+        .await;
+        if sender.is_closed() {
+            break;
+        }
+        match result {
+            Ok(()) => {
+                eprintln!("Log subscription for {} closed, reconnecting", program);
+                endpoints.record_failure(index, "subscription closed".to_string());
+            }
+            Err(e) => {
+                eprintln!("Log subscription for {} failed: {}", program, e);
+                endpoints.record_failure(index, e.to_string());
+            }
+        }
+
+        if !sender.is_closed() {
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    }
+}
+
+async fn stream_logs(
+    endpoints: &EndpointPool,
+    index: usize,
+    websocket_url: &str,
+    program: &Pubkey,
+    seen: &mut SeenSignatures,
+    last_signature: &mut Option<String>,
+    sender: &mpsc::Sender<LogEvent>,
+) -> Result<(), ListenerError> {
+    let client = PubsubClient::new(websocket_url).await?;
+    let (mut stream, unsubscribe) = client
+        .logs_subscribe(
+            RpcTransactionLogsFilter::Mentions(vec![program.to_string()]),
+            RpcTransactionLogsConfig {
+                commitment: Some(CommitmentConfig::confirmed()),
+            },
+        )
+        .await?;
+    endpoints.record_success(index);
+
+    while let Some(response) = stream.next().await {
+        let value = response.value;
+        if !seen.insert(&value.signature) {
+            continue;
+        }
+        *last_signature = Some(value.signature.clone());
+
+        let event = LogEvent {
+            signature: value.signature,
+            slot: response.context.slot,
+            failed: value.err.is_some(),
+            logs: value.logs,
+            backfilled: false,
+        };
+        if sender.send(event).await.is_err() {
+            break;
+        }
+    }
+
+    drop(stream);
+    unsubscribe().await;
+    Ok(())
+}
+
+/// Replays, oldest first, every signature for `program` newer than `until`
+/// that has not been emitted yet.
+async fn backfill(
+    rpc_client: &RpcClient,
+    program: &Pubkey,
+    until: &str,
+    seen: &mut SeenSignatures,
+    last_signature: &mut Option<String>,
+    sender: &mpsc::Sender<LogEvent>,
+) -> Result<(), ListenerError> {
+    let until = Signature::from_str(until)?;
+    let mut missed = Vec::new();
+    let mut before = None;
+
+    // Signatures come back newest first, at most 1000 per page.
+    loop {
+        let page = rpc_client
+            .get_signatures_for_address_with_config(
+                program,
+                GetConfirmedSignaturesForAddress2Config {
+                    before,
+                    until: Some(until),
+                    limit: None,
+                    commitment: Some(CommitmentConfig::confirmed()),
+                },
+            )
+            .await?;
+        let Some(oldest) = page.last() else {
+            break;
+        };
+        before = Some(Signature::from_str(&oldest.signature)?);
+        missed.extend(page);
+    }
+
+    for status in missed.into_iter().rev() {
+        if seen.contains(&status.signature) {
+            continue;
+        }
+
+        let signature = Signature::from_str(&status.signature)?;
+        let transaction = rpc_client
+            .get_transaction_with_config(
+                &signature,
+                RpcTransactionConfig {
+                    encoding: Some(UiTransactionEncoding::Json),
+                    commitment: Some(CommitmentConfig::confirmed()),
+                    max_supported_transaction_version: Some(0),
+                },
+            )
+            .await?;
+        let logs = match transaction.transaction.meta.map(|meta| meta.log_messages) {
+            Some(OptionSerializer::Some(logs)) => logs,
+            _ => Vec::new(),
+        };
+
+        seen.insert(&status.signature);
+        *last_signature = Some(status.signature.clone());
+        let event = LogEvent {
+            signature: status.signature,
+            slot: status.slot,
+            failed: status.err.is_some(),
+            logs,
+            backfilled: true,
+        };
+        if sender.send(event).await.is_err() {
+            break;
+        }
+    }
+
+    Ok(())
+}
@@ -1,18 +1,61 @@
-mod config;
-mod token_service;
+use dca_listener::config::Config;
+use dca_listener::token_service::{LookupEvent, TokenService};
+use std::time::Duration;
+use tokio::sync::mpsc;
 
-use config::Config;
-use token_service::TokenService;
-use tokio_postgres::NoTls;
+/// How long background tasks get to stop after a shutdown signal.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let config = Config::load()?;
+    let token_service = TokenService::new(&config).await?;
+
+    let result = tokio::select! {
+        result = run(&token_service) => result,
+        signal = shutdown_signal() => {
+            signal?;
+            println!("Shutdown signal received, stopping");
+            Ok(())
+        }
+    };
+
+    token_service.shutdown(SHUTDOWN_TIMEOUT).await;
+    result
+}
+
+async fn run(token_service: &TokenService) -> Result<(), Box<dyn std::error::Error>> {
+    token_service.check_readiness().await?;
+    println!("Service ready");
+
     let mint = "61V8vBaqAGMpgDQi4JcAwo1dmBGHsyhzodcPqnEVpump";
-    let token_service = TokenService::new(&config.rpc_url, &config.db_url).await?;
-    let metadata = token_service.get_metadata(mint).await?;
-    println!("Metadata: {:?}", metadata);
-    let price = token_service.get_price(mint).await?;
-    println!("Price (cached): {:?}", price);
-    Ok(())
+    let (sender, mut receiver) = mpsc::channel(16);
+    let print = async {
+        while let Some(event) = receiver.recv().await {
+            match event {
+                LookupEvent::Metadata(metadata) => println!("Metadata: {:?}", metadata),
+                LookupEvent::Price { price, .. } => println!("Price (cached): {:?}", price),
+            }
+        }
+    };
+
+    let (result, ()) = tokio::join!(token_service.lookup(mint, sender), print);
+    result
+}
+
+/// Resolves on Ctrl-C, or SIGTERM on unix.
+async fn shutdown_signal() -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        let mut terminate =
+            tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())?;
+        tokio::select! {
+            result = tokio::signal::ctrl_c() => result,
+            _ = terminate.recv() => Ok(()),
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        tokio::signal::ctrl_c().await
+    }
 }
@@ -0,0 +1,157 @@
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Prefix of every exported metric name.
+const NAMESPACE: &str = "dca_listener";
+/// Most recent cache lookups remembered for `TokenService::hit_ratio`; older
+/// ones fall out even if still inside the requested window.
+const LOOKUP_LOG_CAPACITY: usize = 10_000;
+
+/// Cache hits and misses within a rolling window.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct HitRatio {
+    pub hits: u64,
+    pub misses: u64,
+    /// `hits / (hits + misses)`, `None` when there were no lookups.
+    pub ratio: Option<f64>,
+}
+
+/// Ring buffer of timestamped cache lookups, `true` for a hit.
+#[derive(Debug, Default)]
+pub struct LookupLog {
+    events: Mutex<VecDeque<(Instant, bool)>>,
+}
+
+impl LookupLog {
+    pub fn record(&self, hit: bool) {
+        let mut events = self.events.lock().unwrap();
+        if events.len() == LOOKUP_LOG_CAPACITY {
+            events.pop_front();
+        }
+        events.push_back((Instant::now(), hit));
+    }
+
+    /// Lookups recorded within `window` before `now`.
+    pub fn ratio(&self, window: Duration, now: Instant) -> HitRatio {
+        let events = self.events.lock().unwrap();
+        let (mut hits, mut misses) = (0, 0);
+        for (_, hit) in events
+            .iter()
+            .rev()
+            .take_while(|(at, _)| now.saturating_duration_since(*at) <= window)
+        {
+            if *hit {
+                hits += 1;
+            } else {
+                misses += 1;
+            }
+        }
+        let total = hits + misses;
+        HitRatio {
+            hits,
+            misses,
+            ratio: (total > 0).then(|| hits as f64 / total as f64),
+        }
+    }
+}
+
+/// Counters updated by `TokenService` as it serves requests.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    pub metadata_cache_hits: AtomicU64,
+    pub metadata_cache_misses: AtomicU64,
+    pub metadata_fetch_errors: AtomicU64,
+    pub price_cache_hits: AtomicU64,
+    pub price_cache_misses: AtomicU64,
+    pub price_fetch_errors: AtomicU64,
+    pub oracle_deviations: AtomicU64,
+    /// Metadata and price cache lookups, for window-scoped hit ratios.
+    pub lookups: LookupLog,
+}
+
+impl Metrics {
+    pub fn increment(counter: &AtomicU64) {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Counts a cache hit on `counter` and logs it for `lookups`.
+    pub fn cache_hit(&self, counter: &AtomicU64) {
+        Self::increment(counter);
+        self.lookups.record(true);
+    }
+
+    pub fn cache_miss(&self, counter: &AtomicU64) {
+        Self::increment(counter);
+        self.lookups.record(false);
+    }
+
+    /// Renders every counter in the Prometheus text exposition format.
+    pub fn render(&self, out: &mut String) {
+        let counters = [
+            (
+                "metadata_cache_hits_total",
+                "Metadata lookups served from the cache.",
+                &self.metadata_cache_hits,
+            ),
+            (
+                "metadata_cache_misses_total",
+                "Metadata lookups that had to go on-chain.",
+                &self.metadata_cache_misses,
+            ),
+            (
+                "metadata_fetch_errors_total",
+                "On-chain metadata fetches that failed.",
+                &self.metadata_fetch_errors,
+            ),
+            (
+                "price_cache_hits_total",
+                "Price lookups served from the cache.",
+                &self.price_cache_hits,
+            ),
+            (
+                "price_cache_misses_total",
+                "Price lookups that had to be fetched or derived.",
+                &self.price_cache_misses,
+            ),
+            (
+                "price_fetch_errors_total",
+                "Price API requests that failed or had no entry for the mint.",
+                &self.price_fetch_errors,
+            ),
+            (
+                "oracle_deviations_total",
+                "Fresh API prices further from the mint's oracle than allowed.",
+                &self.oracle_deviations,
+            ),
+        ];
+        for (name, help, counter) in counters {
+            write_metric(
+                out,
+                name,
+                "counter",
+                help,
+                &[("", counter.load(Ordering::Relaxed) as f64)],
+            );
+        }
+    }
+}
+
+/// Appends one metric family, namespaced under `dca_listener_`; each sample
+/// is `(labels, value)`, with labels already formatted as `key="value",...`
+/// or empty.
+pub fn write_metric(out: &mut String, name: &str, kind: &str, help: &str, samples: &[(&str, f64)]) {
+    let name = format!("{}_{}", NAMESPACE, name);
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+    for (labels, value) in samples {
+        if labels.is_empty() {
+            let _ = writeln!(out, "{} {}", name, value);
+        } else {
+            let _ = writeln!(out, "{}{{{}}} {}", name, labels, value);
+        }
+    }
+}
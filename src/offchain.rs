@@ -0,0 +1,187 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Resolver label used when the metadata URI itself served the JSON.
+pub const PRIMARY_RESOLVER: &str = "primary";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OffchainMetadata {
+    pub uri: String,
+    /// Which resolver produced the JSON: `primary` or the gateway base URL.
+    pub resolver: String,
+    pub name: Option<String>,
+    pub symbol: Option<String>,
+    pub description: Option<String>,
+    pub image: Option<String>,
+    pub json: Value,
+}
+
+impl OffchainMetadata {
+    pub fn from_json(uri: &str, resolver: &str, json: Value) -> Self {
+        let field = |key: &str| json[key].as_str().map(|s| s.to_string());
+        Self {
+            uri: uri.to_string(),
+            resolver: resolver.to_string(),
+            name: field("name"),
+            symbol: field("symbol"),
+            description: field("description"),
+            image: field("image"),
+            json,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenLinks {
+    pub website: Option<String>,
+    pub twitter: Option<String>,
+    pub telegram: Option<String>,
+    pub discord: Option<String>,
+}
+
+impl TokenLinks {
+    /// Reads links from the Metaplex `extensions` object, falling back to the
+    /// top-level keys some launchpads (e.g. pump.fun) write, and to
+    /// `external_url` for the website. Blank values count as absent.
+    pub fn from_json(json: &Value) -> Self {
+        let link = |key: &str| {
+            [&json["extensions"][key], &json[key]]
+                .into_iter()
+                .filter_map(|value| value.as_str())
+                .map(str::trim)
+                .find(|value| !value.is_empty())
+                .map(|value| value.to_string())
+        };
+
+        Self {
+            website: link("website").or_else(|| link("external_url")),
+            twitter: link("twitter"),
+            telegram: link("telegram"),
+            discord: link("discord"),
+        }
+    }
+}
+
+/// Extracts the `<cid>[/path]` part of an IPFS URI, whether it is written as
+/// `ipfs://`, a path gateway (`/ipfs/<cid>`) or a subdomain gateway
+/// (`<cid>.ipfs.<host>`).
+pub fn ipfs_path(uri: &str) -> Option<String> {
+    if let Some(rest) = uri.strip_prefix("ipfs://") {
+        let rest = rest.strip_prefix("ipfs/").unwrap_or(rest);
+        return (!rest.is_empty()).then(|| rest.to_string());
+    }
+
+    let without_scheme = uri
+        .strip_prefix("https://")
+        .or_else(|| uri.strip_prefix("http://"))?;
+
+    if let Some((_, rest)) = without_scheme.split_once("/ipfs/") {
+        return (!rest.is_empty()).then(|| rest.to_string());
+    }
+
+    let (host, path) = without_scheme
+        .split_once('/')
+        .unwrap_or((without_scheme, ""));
+    let (cid, _) = host.split_once(".ipfs.")?;
+    Some(if path.is_empty() {
+        cid.to_string()
+    } else {
+        format!("{}/{}", cid, path)
+    })
+}
+
+/// Ordered list of `(resolver, url)` pairs to try for a metadata URI: the URI
+/// itself when it is fetchable over HTTP, then each gateway for IPFS content.
+pub fn candidate_urls(uri: &str, gateways: &[String]) -> Vec<(String, String)> {
+    let mut candidates = Vec::new();
+    if uri.starts_with("http://") || uri.starts_with("https://") {
+        candidates.push((PRIMARY_RESOLVER.to_string(), uri.to_string()));
+    }
+
+    if let Some(path) = ipfs_path(uri) {
+        for gateway in gateways {
+            let base = gateway.trim_end_matches('/');
+            let url = format!("{}/ipfs/{}", base, path);
+            if !candidates.iter().any(|(_, existing)| *existing == url) {
+                candidates.push((base.to_string(), url));
+            }
+        }
+    }
+
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn reads_standard_fields() {
+        let json = json!({"name": "Bonk", "symbol": "BONK", "image": "https://x/i.png"});
+        let metadata = OffchainMetadata::from_json("https://x/m.json", PRIMARY_RESOLVER, json);
+        assert_eq!(metadata.name.as_deref(), Some("Bonk"));
+        assert_eq!(metadata.image.as_deref(), Some("https://x/i.png"));
+        assert_eq!(metadata.description, None);
+    }
+
+    #[test]
+    fn links_prefer_extensions_and_skip_blanks() {
+        let json = json!({
+            "extensions": {"twitter": "https://x.com/bonk", "website": " "},
+            "twitter": "https://x.com/other",
+            "telegram": "https://t.me/bonk",
+            "external_url": "https://bonk.example",
+        });
+        assert_eq!(
+            TokenLinks::from_json(&json),
+            TokenLinks {
+                website: Some("https://bonk.example".to_string()),
+                twitter: Some("https://x.com/bonk".to_string()),
+                telegram: Some("https://t.me/bonk".to_string()),
+                discord: None,
+            }
+        );
+    }
+
+    #[test]
+    fn extracts_ipfs_paths() {
+        assert_eq!(
+            ipfs_path("ipfs://bafy/meta.json").as_deref(),
+            Some("bafy/meta.json")
+        );
+        assert_eq!(ipfs_path("ipfs://ipfs/bafy").as_deref(), Some("bafy"));
+        assert_eq!(
+            ipfs_path("https://gateway.example/ipfs/bafy/1.json").as_deref(),
+            Some("bafy/1.json")
+        );
+        assert_eq!(
+            ipfs_path("https://bafy.ipfs.dweb.link/1.json").as_deref(),
+            Some("bafy/1.json")
+        );
+        assert_eq!(ipfs_path("https://arweave.net/abc"), None);
+        assert_eq!(ipfs_path("ipfs://"), None);
+    }
+
+    #[test]
+    fn candidates_dedupe_gateways() {
+        let gateways = vec![
+            "https://gateway.example/".to_string(),
+            "https://ipfs.io".to_string(),
+        ];
+        assert_eq!(
+            candidate_urls("https://gateway.example/ipfs/bafy", &gateways),
+            vec![
+                (
+                    PRIMARY_RESOLVER.to_string(),
+                    "https://gateway.example/ipfs/bafy".to_string()
+                ),
+                (
+                    "https://ipfs.io".to_string(),
+                    "https://ipfs.io/ipfs/bafy".to_string()
+                ),
+            ]
+        );
+        assert!(candidate_urls("ar://abc", &gateways).is_empty());
+    }
+}
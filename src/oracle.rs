@@ -0,0 +1,115 @@
+use crate::programs;
+use solana_sdk::account::Account;
+use solana_sdk::hash::hash;
+
+/// USD price published by a Pyth pull oracle.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OraclePrice {
+    pub price: f64,
+    pub confidence: f64,
+    /// Unix timestamp the price was published at.
+    pub publish_time: i64,
+}
+
+impl OraclePrice {
+    /// Decodes a Pyth `PriceUpdateV2` account; `None` for accounts of any
+    /// other owner or layout.
+    pub fn from_pyth_account(account: &Account) -> Option<Self> {
+        if account.owner != programs::PYTH_RECEIVER {
+            return None;
+        }
+        let discriminator = &hash(b"account:PriceUpdateV2").to_bytes()[..8];
+        // Write authority, then the verification level: `Partial` carries
+        // its signature count, `Full` nothing.
+        let rest = account.data.strip_prefix(discriminator)?.get(32..)?;
+        let message = match rest.first()? {
+            0 => rest.get(2..)?,
+            1 => rest.get(1..)?,
+            _ => return None,
+        };
+
+        // Feed id, then price, confidence, exponent and publish time.
+        let bytes =
+            |offset: usize| -> Option<[u8; 8]> { message.get(offset..offset + 8)?.try_into().ok() };
+        let price = i64::from_le_bytes(bytes(32)?);
+        let confidence = u64::from_le_bytes(bytes(40)?);
+        let exponent = i32::from_le_bytes(message.get(48..52)?.try_into().ok()?);
+        let publish_time = i64::from_le_bytes(bytes(52)?);
+
+        let scale = 10f64.powi(exponent);
+        Some(Self {
+            price: price as f64 * scale,
+            confidence: confidence as f64 * scale,
+            publish_time,
+        })
+    }
+
+    /// How far `price` is from the oracle price, in percent of the latter.
+    pub fn deviation_pct(&self, price: f64) -> f64 {
+        ((price - self.price) / self.price).abs() * 100.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::pubkey::Pubkey;
+
+    fn price_update(verification: &[u8], price: i64, exponent: i32) -> Account {
+        let mut data = hash(b"account:PriceUpdateV2").to_bytes()[..8].to_vec();
+        data.extend([0u8; 32]);
+        data.extend(verification);
+        data.extend([3u8; 32]);
+        data.extend(price.to_le_bytes());
+        data.extend(250u64.to_le_bytes());
+        data.extend(exponent.to_le_bytes());
+        data.extend(1_700_000_000i64.to_le_bytes());
+        Account {
+            lamports: 1,
+            data,
+            owner: programs::PYTH_RECEIVER,
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    #[test]
+    fn decodes_fully_verified_update() {
+        let oracle = OraclePrice::from_pyth_account(&price_update(&[1], 15_025, -2)).unwrap();
+        assert!((oracle.price - 150.25).abs() < 1e-9);
+        assert!((oracle.confidence - 2.5).abs() < 1e-9);
+        assert_eq!(oracle.publish_time, 1_700_000_000);
+    }
+
+    #[test]
+    fn decodes_partially_verified_update() {
+        let oracle = OraclePrice::from_pyth_account(&price_update(&[0, 5], 42, 0)).unwrap();
+        assert_eq!(oracle.price, 42.0);
+    }
+
+    #[test]
+    fn rejects_other_owners_and_layouts() {
+        let mut account = price_update(&[1], 42, 0);
+        account.owner = Pubkey::new_unique();
+        assert_eq!(OraclePrice::from_pyth_account(&account), None);
+
+        let mut account = price_update(&[1], 42, 0);
+        account.data[0] ^= 0xff;
+        assert_eq!(OraclePrice::from_pyth_account(&account), None);
+
+        let mut account = price_update(&[1], 42, 0);
+        account.data.truncate(account.data.len() - 4);
+        assert_eq!(OraclePrice::from_pyth_account(&account), None);
+    }
+
+    #[test]
+    fn deviation_is_relative_to_oracle() {
+        let oracle = OraclePrice {
+            price: 100.0,
+            confidence: 0.0,
+            publish_time: 0,
+        };
+        assert!((oracle.deviation_pct(95.0) - 5.0).abs() < 1e-9);
+        assert!((oracle.deviation_pct(110.0) - 10.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,18 @@
+use solana_sdk::pubkey;
+use solana_sdk::pubkey::Pubkey;
+
+pub const SPL_TOKEN: Pubkey = pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+pub const TOKEN_2022: Pubkey = pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+pub const ASSOCIATED_TOKEN: Pubkey = pubkey!("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
+/// Metaplex Token Metadata, owner of every metadata PDA.
+pub const TOKEN_METADATA: Pubkey = pubkey!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+pub const JUPITER_DCA: Pubkey = pubkey!("DCA265Vj8a9CEuX1eb1LWRnDT7uK6q1xMipnNyatn23M");
+/// Pyth pull oracle receiver, owner of `PriceUpdateV2` accounts.
+pub const PYTH_RECEIVER: Pubkey = pubkey!("rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ");
+/// Pump.fun bonding curve program.
+pub const PUMP_FUN: Pubkey = pubkey!("6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P");
+
+/// Whether `program` is one of the two SPL token programs.
+pub fn is_token_program(program: &Pubkey) -> bool {
+    *program == SPL_TOKEN || *program == TOKEN_2022
+}
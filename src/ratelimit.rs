@@ -0,0 +1,118 @@
+use async_trait::async_trait;
+use reqwest_middleware::{Middleware, Next};
+use rpc_reqwest::header::HeaderMap;
+use rpc_reqwest::{Request, Response};
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use task_local_extensions::Extensions;
+
+/// Requests start being spaced out once less than this fraction of the
+/// budget is left.
+const THROTTLE_BELOW: f64 = 0.1;
+/// Pause per request when the provider reports no reset time.
+const DEFAULT_DELAY: Duration = Duration::from_millis(250);
+/// Longest pause before any single request.
+const MAX_DELAY: Duration = Duration::from_secs(2);
+
+/// Request budget as last reported in the RPC provider's rate-limit headers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RateLimitBudget {
+    pub remaining: u64,
+    pub limit: u64,
+    /// Seconds until the budget resets, counted from when it was reported.
+    pub reset_secs: Option<u64>,
+}
+
+struct Observed {
+    budget: RateLimitBudget,
+    at: Instant,
+}
+
+/// Reads `x-ratelimit-*` (or the IETF draft `ratelimit-*`) headers off every
+/// RPC response and, once the budget runs low, spreads the rest of it over
+/// the time left until the reset instead of running into hard 429s.
+#[derive(Default)]
+pub struct RateLimiter {
+    last: Mutex<Option<Observed>>,
+}
+
+impl RateLimiter {
+    /// `None` until a response carried rate-limit headers.
+    pub fn budget(&self) -> Option<RateLimitBudget> {
+        let last = self.last.lock().unwrap();
+        last.as_ref().map(|observed| observed.budget.clone())
+    }
+
+    /// How long to hold the next request back, if at all.
+    fn delay(&self) -> Option<Duration> {
+        let last = self.last.lock().unwrap();
+        let Observed { budget, at } = last.as_ref()?;
+        if budget.limit == 0 || budget.remaining as f64 / budget.limit as f64 >= THROTTLE_BELOW {
+            return None;
+        }
+
+        let delay = match budget.reset_secs {
+            Some(reset_secs) => {
+                let left = Duration::from_secs(reset_secs).saturating_sub(at.elapsed());
+                if left.is_zero() {
+                    return None;
+                }
+                left / (budget.remaining as u32).saturating_add(1)
+            }
+            None => DEFAULT_DELAY,
+        };
+        Some(delay.min(MAX_DELAY))
+    }
+
+    fn observe(&self, headers: &HeaderMap) {
+        let (Some(remaining), Some(limit)) = (
+            rate_limit_header(headers, "remaining"),
+            rate_limit_header(headers, "limit"),
+        ) else {
+            return;
+        };
+        *self.last.lock().unwrap() = Some(Observed {
+            budget: RateLimitBudget {
+                remaining,
+                limit,
+                reset_secs: rate_limit_header(headers, "reset"),
+            },
+            at: Instant::now(),
+        });
+    }
+}
+
+/// Leading integer of `x-ratelimit-<name>` or `ratelimit-<name>`; values such
+/// as `100, 100;w=60` carry extra policies after the first number.
+fn rate_limit_header(headers: &HeaderMap, name: &str) -> Option<u64> {
+    [
+        format!("x-ratelimit-{}", name),
+        format!("ratelimit-{}", name),
+    ]
+    .iter()
+    .filter_map(|key| headers.get(key.as_str())?.to_str().ok())
+    .find_map(|value| {
+        let end = value
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(value.len());
+        value[..end].parse().ok()
+    })
+}
+
+#[async_trait]
+impl Middleware for RateLimiter {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        if let Some(delay) = self.delay() {
+            tokio::time::sleep(delay).await;
+        }
+        let response = next.run(req, extensions).await?;
+        self.observe(response.headers());
+        Ok(response)
+    }
+}
@@ -0,0 +1,57 @@
+use crate::token_service::TokenMetadata;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum RiskLevel {
+    Low,
+    Medium,
+    High,
+}
+
+impl RiskLevel {
+    /// Risk from the signals cached with the metadata:
+    /// - High: a freeze authority, or a mint authority on mutable metadata.
+    /// - Medium: a mint authority, mutable metadata, or listed creators none
+    ///   of whom verified.
+    /// - Low: none of the above.
+    pub fn assess(metadata: &TokenMetadata) -> Self {
+        let mint_authority = metadata.mint_authority.is_some();
+        if metadata.freeze_authority.is_some() || (mint_authority && metadata.is_mutable) {
+            Self::High
+        } else if mint_authority || metadata.is_mutable || metadata.creators_verified == Some(false)
+        {
+            Self::Medium
+        } else {
+            Self::Low
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Low => "low",
+            Self::Medium => "medium",
+            Self::High => "high",
+        }
+    }
+}
+
+impl fmt::Display for RiskLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for RiskLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "low" => Ok(Self::Low),
+            "medium" => Ok(Self::Medium),
+            "high" => Ok(Self::High),
+            other => Err(format!("Unknown risk level: {}", other)),
+        }
+    }
+}
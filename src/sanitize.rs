@@ -0,0 +1,141 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+
+/// Characters that render as nothing but survive `str::trim`.
+fn is_invisible(c: char) -> bool {
+    matches!(
+        c,
+        '\u{200B}' | '\u{200C}' | '\u{200D}' | '\u{2060}' | '\u{FEFF}' | '\u{00AD}'
+    )
+}
+
+/// Strips the NUL padding Metaplex uses for fixed-size string fields.
+pub fn trim_padding(value: &str) -> &str {
+    value.trim_matches(char::from(0))
+}
+
+/// Longest prefix of `bytes` that is valid UTF-8, dropping a multibyte
+/// character cut short at the end (or anything after the first bad byte).
+pub fn utf8_prefix(bytes: &[u8]) -> &str {
+    match std::str::from_utf8(bytes) {
+        Ok(value) => value,
+        Err(e) => std::str::from_utf8(&bytes[..e.valid_up_to()]).unwrap_or_default(),
+    }
+}
+
+/// True when the string has no visible characters once whitespace and
+/// zero-width characters are ignored.
+pub fn is_blank(value: &str) -> bool {
+    value.chars().all(|c| c.is_whitespace() || is_invisible(c))
+}
+
+/// Display stand-in for a token without a usable symbol, e.g. `61V8…pump`.
+pub fn short_address(mint: &str) -> String {
+    if mint.len() <= 8 {
+        return mint.to_string();
+    }
+    format!("{}…{}", &mint[..4], &mint[mint.len() - 4..])
+}
+
+/// Cuts `value`, once trimmed, to at most `max_chars` characters, the last
+/// being an ellipsis. Returns `None` when it already fits or `max_chars` is 0.
+pub fn truncate_chars(value: &str, max_chars: usize) -> Option<String> {
+    let trimmed = value.trim();
+    if max_chars == 0 || trimmed.chars().count() <= max_chars {
+        return None;
+    }
+    let kept: String = trimmed.chars().take(max_chars - 1).collect();
+    Some(format!("{}…", kept.trim_end()))
+}
+
+/// Decodes names stored in a known vanity encoding, returning `None` when
+/// the value matches none of them. Decoders, tried in order:
+///
+/// - base64: the whole value, optionally prefixed `base64:`, is standard
+///   base64 of at least 8 characters that decodes to printable UTF-8;
+/// - escaped code points: `\uXXXX` escapes, including surrogate pairs for
+///   emoji, written out literally instead of as the characters themselves.
+pub fn decode_vanity(value: &str) -> Option<String> {
+    decode_base64_text(value).or_else(|| decode_unicode_escapes(value))
+}
+
+fn decode_base64_text(value: &str) -> Option<String> {
+    let encoded = value.strip_prefix("base64:").unwrap_or(value);
+    if encoded.len() < 8 || !encoded.len().is_multiple_of(4) {
+        return None;
+    }
+    let decoded = String::from_utf8(BASE64.decode(encoded).ok()?).ok()?;
+    (!decoded.trim().is_empty() && !decoded.chars().any(char::is_control)).then_some(decoded)
+}
+
+fn decode_unicode_escapes(value: &str) -> Option<String> {
+    if !value.contains("\\u") {
+        return None;
+    }
+    let mut units = Vec::new();
+    let mut rest = value;
+    while let Some(index) = rest.find("\\u") {
+        units.extend(rest[..index].encode_utf16());
+        let hex = rest.get(index + 2..index + 6)?;
+        units.push(u16::from_str_radix(hex, 16).ok()?);
+        rest = &rest[index + 6..];
+    }
+    units.extend(rest.encode_utf16());
+    String::from_utf16(&units).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trims_nul_padding_only() {
+        assert_eq!(trim_padding("Bonk\0\0\0"), "Bonk");
+        assert_eq!(trim_padding(" Bonk \0"), " Bonk ");
+    }
+
+    #[test]
+    fn utf8_prefix_drops_cut_character() {
+        let bytes = "ab€".as_bytes();
+        assert_eq!(utf8_prefix(bytes), "ab€");
+        assert_eq!(utf8_prefix(&bytes[..bytes.len() - 1]), "ab");
+    }
+
+    #[test]
+    fn zero_width_only_is_blank() {
+        assert!(is_blank(" \u{200B}\u{FEFF} "));
+        assert!(!is_blank("\u{200B}A"));
+    }
+
+    #[test]
+    fn shortens_long_addresses() {
+        assert_eq!(
+            short_address("61V8vBaqAGMpgDQi4JcAwo1dmBGHsyhzodcPqnEVpump"),
+            "61V8…pump"
+        );
+        assert_eq!(short_address("BONK"), "BONK");
+    }
+
+    #[test]
+    fn truncates_by_characters() {
+        assert_eq!(truncate_chars("  Bonk  ", 4), None);
+        assert_eq!(truncate_chars("Bonk Inu", 5), Some("Bonk…".to_string()));
+        assert_eq!(truncate_chars("🐶🐶🐶", 2), Some("🐶…".to_string()));
+        assert_eq!(truncate_chars("Bonk Inu", 0), None);
+    }
+
+    #[test]
+    fn decodes_vanity_encodings() {
+        assert_eq!(decode_vanity("Qm9uayBJbnU="), Some("Bonk Inu".to_string()));
+        assert_eq!(
+            decode_vanity("base64:Qm9uayBJbnU="),
+            Some("Bonk Inu".to_string())
+        );
+        assert_eq!(
+            decode_vanity("\\ud83d\\udc36 Dog"),
+            Some("🐶 Dog".to_string())
+        );
+        assert_eq!(decode_vanity("Bonk"), None);
+        assert_eq!(decode_vanity("\\u12"), None);
+    }
+}
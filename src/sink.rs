@@ -0,0 +1,47 @@
+use async_trait::async_trait;
+use std::sync::Arc;
+use tokio_postgres::Client;
+
+pub type SinkError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Receives every freshly fetched USD price. Cached reads are not replayed.
+#[async_trait]
+pub trait PriceSink: Send + Sync {
+    /// `observed_at` is a unix timestamp in seconds.
+    async fn record(&self, mint: &str, price: f64, observed_at: i64) -> Result<(), SinkError>;
+}
+
+/// Default sink: appends to the `token_price_history` table that adaptive
+/// TTLs and volatility stats read from. With a sample interval, an
+/// observation is only written once the mint's last row is at least that
+/// old, however often prices are fetched.
+pub struct PostgresHistorySink {
+    db_client: Arc<Client>,
+    sample_interval_secs: i64,
+}
+
+impl PostgresHistorySink {
+    pub fn new(db_client: Arc<Client>, sample_interval_secs: u64) -> Self {
+        Self {
+            db_client,
+            sample_interval_secs: sample_interval_secs as i64,
+        }
+    }
+}
+
+#[async_trait]
+impl PriceSink for PostgresHistorySink {
+    async fn record(&self, mint: &str, price: f64, observed_at: i64) -> Result<(), SinkError> {
+        self.db_client
+            .execute(
+                "INSERT INTO token_price_history (mint, price, observed_at) 
+                 SELECT $1::TEXT, $2::DOUBLE PRECISION, $3::BIGINT WHERE NOT EXISTS ( 
+                     SELECT 1 FROM token_price_history 
+                     WHERE mint = $1 AND observed_at > $3 - $4 
+                 )",
+                &[&mint, &price, &observed_at, &self.sample_interval_secs],
+            )
+            .await?;
+        Ok(())
+    }
+}
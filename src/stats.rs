@@ -0,0 +1,177 @@
+use crate::config::PriceConfig;
+
+/// Standard deviation, in percent, of the simple returns between consecutive
+/// prices. `None` with fewer than three points or any non-positive price.
+pub fn return_volatility(prices: &[f64]) -> Option<f64> {
+    if prices.len() < 3 || prices.iter().any(|price| *price <= 0.0) {
+        return None;
+    }
+
+    let returns: Vec<f64> = prices
+        .windows(2)
+        .map(|pair| (pair[1] - pair[0]) / pair[0] * 100.0)
+        .collect();
+    let mean = returns.iter().sum::<f64>() / returns.len() as f64;
+    let variance =
+        returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (returns.len() - 1) as f64;
+    Some(variance.sqrt())
+}
+
+/// Scales `base_ttl` inversely with volatility: a series moving exactly
+/// `reference_volatility_pct` per sample keeps `base_ttl`, choppier series get
+/// shorter TTLs and calmer ones longer, clamped to the configured bounds.
+pub fn adaptive_ttl(prices: &[f64], base_ttl: u64, config: &PriceConfig) -> u64 {
+    let ttl = match return_volatility(prices) {
+        None => base_ttl as f64,
+        Some(volatility) if volatility <= f64::EPSILON => config.max_ttl_secs as f64,
+        Some(volatility) => base_ttl as f64 * config.reference_volatility_pct / volatility,
+    };
+    ttl.clamp(config.min_ttl_secs as f64, config.max_ttl_secs as f64) as u64
+}
+
+/// Time-weighted average over `[start, end]` of a step series: each
+/// `(timestamp, price)` point, sorted by timestamp, holds until the next one
+/// and the last holds until `end`. A point before `start` supplies the price
+/// in effect when the window opens. `None` without points; a window of zero
+/// length yields the latest price.
+pub fn time_weighted_average(points: &[(i64, f64)], start: i64, end: i64) -> Option<f64> {
+    let (_, latest) = *points.last()?;
+
+    let mut weighted = 0.0;
+    let mut total = 0.0;
+    for (index, (observed_at, price)) in points.iter().enumerate() {
+        let until = points
+            .get(index + 1)
+            .map_or(end, |(next, _)| *next)
+            .min(end);
+        let from = (*observed_at).max(start);
+        if until > from {
+            let duration = (until - from) as f64;
+            weighted += price * duration;
+            total += duration;
+        }
+    }
+
+    Some(if total > 0.0 {
+        weighted / total
+    } else {
+        latest
+    })
+}
+
+/// Pairs every point of `a` with the latest point of `b` at or before it,
+/// skipping `a` points that precede all of `b`. Both series must be sorted by
+/// timestamp.
+pub fn align_as_of(a: &[(i64, f64)], b: &[(i64, f64)]) -> Vec<(f64, f64)> {
+    let mut aligned = Vec::with_capacity(a.len());
+    let mut next_b = 0;
+    for (observed_at, price_a) in a {
+        while next_b < b.len() && b[next_b].0 <= *observed_at {
+            next_b += 1;
+        }
+        if next_b > 0 {
+            aligned.push((*price_a, b[next_b - 1].1));
+        }
+    }
+    aligned
+}
+
+/// Pearson correlation of the simple returns of two aligned price series.
+/// `None` with fewer than `min_samples` returns, any non-positive price, or a
+/// series whose returns never vary.
+pub fn return_correlation(aligned: &[(f64, f64)], min_samples: usize) -> Option<f64> {
+    if aligned.iter().any(|(a, b)| *a <= 0.0 || *b <= 0.0) {
+        return None;
+    }
+    let returns: Vec<(f64, f64)> = aligned
+        .windows(2)
+        .map(|pair| {
+            (
+                (pair[1].0 - pair[0].0) / pair[0].0,
+                (pair[1].1 - pair[0].1) / pair[0].1,
+            )
+        })
+        .collect();
+    if returns.len() < min_samples.max(2) {
+        return None;
+    }
+
+    let count = returns.len() as f64;
+    let mean_a = returns.iter().map(|(a, _)| a).sum::<f64>() / count;
+    let mean_b = returns.iter().map(|(_, b)| b).sum::<f64>() / count;
+    let (mut covariance, mut variance_a, mut variance_b) = (0.0, 0.0, 0.0);
+    for (a, b) in &returns {
+        covariance += (a - mean_a) * (b - mean_b);
+        variance_a += (a - mean_a).powi(2);
+        variance_b += (b - mean_b).powi(2);
+    }
+    if variance_a <= f64::EPSILON || variance_b <= f64::EPSILON {
+        return None;
+    }
+    Some(covariance / (variance_a * variance_b).sqrt())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn volatility_needs_three_positive_prices() {
+        assert_eq!(return_volatility(&[1.0, 2.0]), None);
+        assert_eq!(return_volatility(&[1.0, 0.0, 2.0]), None);
+        assert!(close(
+            return_volatility(&[100.0, 101.0, 102.01]).unwrap(),
+            0.0
+        ));
+    }
+
+    #[test]
+    fn adaptive_ttl_is_clamped() {
+        let config = PriceConfig::default();
+        assert_eq!(adaptive_ttl(&[1.0], 60, &config), 60);
+        assert_eq!(
+            adaptive_ttl(&[100.0, 100.0, 100.0], 60, &config),
+            config.max_ttl_secs
+        );
+        assert_eq!(
+            adaptive_ttl(&[100.0, 150.0, 90.0, 160.0], 60, &config),
+            config.min_ttl_secs
+        );
+    }
+
+    #[test]
+    fn twap_weights_by_duration() {
+        let points = [(0, 10.0), (10, 20.0)];
+        assert!(close(time_weighted_average(&points, 0, 20).unwrap(), 15.0));
+        assert!(close(
+            time_weighted_average(&points, 5, 20).unwrap(),
+            50.0 / 3.0
+        ));
+        assert!(close(time_weighted_average(&points, 20, 20).unwrap(), 20.0));
+        assert_eq!(time_weighted_average(&[], 0, 10), None);
+    }
+
+    #[test]
+    fn aligns_to_latest_earlier_point() {
+        let a = [(1, 1.0), (5, 2.0), (9, 3.0)];
+        let b = [(2, 10.0), (8, 20.0)];
+        assert_eq!(align_as_of(&a, &b), vec![(2.0, 10.0), (3.0, 20.0)]);
+    }
+
+    #[test]
+    fn correlation_of_matching_and_opposite_moves() {
+        let same = [(1.0, 2.0), (1.1, 2.2), (1.0, 2.0), (1.2, 2.4)];
+        assert!(close(return_correlation(&same, 2).unwrap(), 1.0));
+
+        let opposite = [(1.0, 1.0), (1.1, 0.9), (1.0, 1.0), (1.1, 0.9)];
+        assert!(return_correlation(&opposite, 2).unwrap() < -0.99);
+
+        assert_eq!(return_correlation(&same, 10), None);
+        let flat = [(1.0, 1.0), (1.1, 1.0), (1.0, 1.0)];
+        assert_eq!(return_correlation(&flat, 2), None);
+    }
+}
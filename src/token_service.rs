@@ -1,45 +1,678 @@
+use crate::activity::{self, MintActivity};
+use crate::coalesce::{self, PriceCoalescer};
+use crate::codec::CacheCodecs;
+use crate::config::{
+    Config, MetadataConfig, OffchainConfig, PriceConfig, ReadinessConfig, TimeoutConfig,
+    TradeabilityConfig,
+};
+use crate::das::{self, CollectionUpdate, CompressedAsset};
+use crate::dca::{self, DcaEvent, DcaFill, DcaFillExpectation, DcaOrderEvent};
+use crate::error::TokenServiceError;
+use crate::http;
+use crate::labels;
+use crate::listener::{self, EndpointHealth, EndpointPool, LogEvent};
+use crate::metrics::{self, HitRatio, Metrics};
+use crate::offchain::{self, OffchainMetadata, TokenLinks};
+use crate::oracle::OraclePrice;
+use crate::programs;
+use crate::ratelimit::RateLimitBudget;
+use crate::risk::RiskLevel;
+use crate::sanitize;
+use crate::sink::{PostgresHistorySink, PriceSink};
+use crate::stats;
+use crate::tradeability::{self, Tradeability, TradeabilitySignals};
+use futures_util::{StreamExt, TryStreamExt};
 use mpl_token_metadata::accounts::Metadata;
-use reqwest;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use solana_client::rpc_client::RpcClient;
+use solana_client::client_error::{ClientError, ClientErrorKind};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_client::GetConfirmedSignaturesForAddress2Config;
+use solana_client::rpc_config::RpcTransactionConfig;
+use solana_client::rpc_custom_error::JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY;
+use solana_client::rpc_request::{RpcError, RpcResponseErrorData, TokenAccountsFilter};
+use solana_client::rpc_response::RpcInflationRate;
+use solana_sdk::account::Account;
+use solana_sdk::epoch_info::EpochInfo;
+use solana_sdk::hash::Hasher;
 use solana_sdk::program_pack::Pack;
 use solana_sdk::pubkey::Pubkey;
-use spl_token::state::Mint;
+use solana_sdk::signature::Signature;
+use solana_transaction_status::option_serializer::OptionSerializer;
+use solana_transaction_status::UiTransactionEncoding;
+use spl_token_2022::extension::default_account_state::DefaultAccountState;
+use spl_token_2022::extension::transfer_fee::TransferFeeConfig;
+use spl_token_2022::extension::{BaseStateWithExtensions, StateWithExtensions};
+use spl_token_2022::state::{Account as TokenAccount, AccountState, Mint};
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
 use std::str::FromStr;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+use tokio::sync::{mpsc, watch, Semaphore};
+use tokio::task::JoinHandle;
+use tokio_postgres::error::SqlState;
 use tokio_postgres::{Client, NoTls};
 
+const USDC_MINT: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+const USDC_DECIMALS: i32 = 6;
+/// Page size cap of `getSignaturesForAddress`.
+const SIGNATURES_PAGE_LIMIT: usize = 1000;
+/// Pages walked back when looking for a mint's first transaction.
+const AGE_SCAN_MAX_PAGES: usize = 20;
+/// Signature scans `get_token_ages` runs at once.
+const AGE_SCAN_CONCURRENCY: usize = 4;
+/// Transactions `recent_activity` fetches at once.
+const ACTIVITY_FETCH_CONCURRENCY: usize = 8;
+/// Key cap of `getMultipleAccounts`.
+const MULTIPLE_ACCOUNTS_LIMIT: usize = 100;
+const JUPITER_PRICE_URL: &str = "https://api.jup.ag/price/v2";
+/// Fewest aligned returns `correlation` will compute a coefficient from.
+const MIN_CORRELATION_SAMPLES: usize = 10;
+/// Base fee of the fill transaction, which the DCA keeper signs once.
+const LAMPORTS_PER_SIGNATURE: u64 = 5_000;
+/// Compute units budgeted for one fill (the DCA instruction plus the swap).
+const DCA_FILL_COMPUTE_UNITS: u64 = 300_000;
+/// Jupiter DCA's platform fee, taken from each fill's output.
+const DCA_PLATFORM_FEE_BPS: u64 = 10;
+
+/// Quote currency the price API reports in natively.
+pub const USD: &str = "USD";
+
+/// Bumped whenever `TokenMetadata` gains a field that cached rows cannot be
+/// defaulted for; rows written under an older version are treated as misses
+/// and refetched.
+pub const METADATA_VERSION: u32 = 6;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TokenMetadata {
+    #[serde(default)]
+    pub version: u32,
     pub mint: String,
     pub name: String,
+    /// The on-chain name, kept when `name` was decoded from a vanity
+    /// encoding.
+    #[serde(default)]
+    pub raw_name: Option<String>,
     pub symbol: String,
     pub decimals: u8,
     pub supply: u64,
+    /// `supply` scaled by `decimals`, as a trimmed decimal string so it
+    /// keeps full precision in JSON.
+    #[serde(default)]
+    pub ui_supply: String,
+    #[serde(default)]
+    pub uri: String,
+    #[serde(default)]
+    pub mint_authority: Option<String>,
+    #[serde(default)]
+    pub freeze_authority: Option<String>,
+    #[serde(default)]
+    pub update_authority: String,
+    /// Set when the on-chain symbol was non-empty but had no visible
+    /// characters, a common trick to spoof or hide a ticker.
+    #[serde(default)]
+    pub symbol_suspicious: bool,
+    /// Metaplex royalty, in basis points.
+    #[serde(default)]
+    pub seller_fee_basis_points: u16,
+    #[serde(default)]
+    pub is_mutable: bool,
+    /// Whether any listed creator signed the metadata; `None` when no
+    /// creators are listed.
+    #[serde(default)]
+    pub creators_verified: Option<bool>,
+    /// Compressed NFT served from DAS; there is no mint account, so supply,
+    /// decimals and authorities are those of a single NFT.
+    #[serde(default)]
+    pub compressed: bool,
+    /// Set when the name or symbol exceeded the configured length limit and
+    /// was cut short.
+    #[serde(default)]
+    pub truncated: bool,
+    /// Soft screening signal: a fungible token whose decimals fall outside
+    /// the configured range.
+    #[serde(default)]
+    pub decimals_unusual: bool,
+}
+
+impl TokenMetadata {
+    /// Stable hash over the fields that make up a token's identity (name,
+    /// symbol, decimals, authorities and URI). Supply is deliberately left out
+    /// so routine mints and burns do not register as an identity change.
+    pub fn fingerprint(&self) -> String {
+        let decimals = [self.decimals];
+        let parts: [&[u8]; 7] = [
+            self.name.as_bytes(),
+            self.symbol.as_bytes(),
+            &decimals,
+            self.mint_authority
+                .as_deref()
+                .unwrap_or_default()
+                .as_bytes(),
+            self.freeze_authority
+                .as_deref()
+                .unwrap_or_default()
+                .as_bytes(),
+            self.update_authority.as_bytes(),
+            self.uri.as_bytes(),
+        ];
+
+        let mut hasher = Hasher::default();
+        for part in parts {
+            hasher.hash(&(part.len() as u64).to_le_bytes());
+            hasher.hash(part);
+        }
+        hasher.result().to_string()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreatorStatus {
+    pub address: String,
+    pub share: u8,
+    pub verified: bool,
+    pub trusted: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreatorVerification {
+    pub mint: String,
+    pub creators: Vec<CreatorStatus>,
+}
+
+impl CreatorVerification {
+    /// True when every listed creator has signed the metadata on-chain.
+    pub fn all_verified(&self) -> bool {
+        self.creators.iter().all(|creator| creator.verified)
+    }
+
+    /// True when at least one creator is both verified and on the trusted list.
+    pub fn has_trusted_verified(&self) -> bool {
+        self.creators
+            .iter()
+            .any(|creator| creator.verified && creator.trusted)
+    }
+}
+
+/// Canonical base58 form of a mint address, used for every cache key so the
+/// same token never lands in two rows.
+pub fn canonical_mint(mint: &str) -> Result<String, Box<dyn std::error::Error>> {
+    Ok(Pubkey::from_str(mint.trim())?.to_string())
+}
+
+/// Token-2022 transfer fee in effect for the current epoch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransferFeeInfo {
+    pub basis_points: u16,
+    /// Cap on the fee per transfer, in raw token units.
+    pub maximum_fee: u64,
+}
+
+impl TransferFeeInfo {
+    pub fn fee_for(&self, raw_amount: u64) -> u64 {
+        let fee = raw_amount as u128 * self.basis_points as u128 / 10_000;
+        (fee as u64).min(self.maximum_fee)
+    }
+}
+
+/// Expected overhead of one DCA fill buying a mint.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CycleCost {
+    pub base_fee_sol: f64,
+    /// Median recent priority fee for the DCA program, over the fill's
+    /// compute budget.
+    pub priority_fee_sol: f64,
+    /// Token-2022 transfer fee withheld from the bought tokens, if any.
+    pub transfer_fee_usd: f64,
+    pub platform_fee_usd: f64,
+    pub total_sol: f64,
+    pub total_usd: f64,
+}
+
+/// Jupiter's `extraInfo.confidenceLevel`; `High` when the field is absent.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PriceConfidence {
+    #[default]
+    High,
+    Medium,
+    Low,
+}
+
+impl PriceConfidence {
+    fn from_level(level: &str) -> Self {
+        match level.to_lowercase().as_str() {
+            "medium" => Self::Medium,
+            "low" => Self::Low,
+            _ => Self::High,
+        }
+    }
+}
+
+/// USD price together with the confidence and last-swap data Jupiter
+/// reports alongside it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriceDetail {
+    pub mint: String,
+    pub price: f64,
+    pub confidence: PriceConfidence,
+    pub last_buy_price: Option<f64>,
+    /// Unix timestamp of the last Jupiter buy.
+    pub last_buy_at: Option<i64>,
+    pub last_sell_price: Option<f64>,
+    /// Unix timestamp of the last Jupiter sell.
+    pub last_sell_at: Option<i64>,
+}
+
+impl PriceDetail {
+    /// Parses one entry of a Jupiter price v2 `data` map.
+    pub fn from_entry(mint: &str, entry: &Value) -> Result<Self, Box<dyn std::error::Error>> {
+        let extra = &entry["extraInfo"];
+        let swap = &extra["lastSwappedPrice"];
+        let price_at = |key: &str| swap[key].as_str().and_then(|s| f64::from_str(s).ok());
+
+        Ok(Self {
+            mint: mint.to_string(),
+            price: parse_price(mint, &entry["price"])?,
+            confidence: extra["confidenceLevel"]
+                .as_str()
+                .map(PriceConfidence::from_level)
+                .unwrap_or_default(),
+            last_buy_price: price_at("lastJupiterBuyPrice"),
+            last_buy_at: swap["lastJupiterBuyAt"].as_i64(),
+            last_sell_price: price_at("lastJupiterSellPrice"),
+            last_sell_at: swap["lastJupiterSellAt"].as_i64(),
+        })
+    }
+}
+
+/// A USD price from `TokenService::get_price_or_last_resort`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum PriceEstimate {
+    /// From the cache or the price API, as `get_price` returns it.
+    Live(f64),
+    /// No live price could be had; the most recent price history entry,
+    /// observed at the unix timestamp `observed_at`.
+    LastResort { price: f64, observed_at: i64 },
+}
+
+impl PriceEstimate {
+    pub fn price(&self) -> f64 {
+        match self {
+            Self::Live(price) | Self::LastResort { price, .. } => *price,
+        }
+    }
+}
+
+fn parse_price(mint: &str, price: &Value) -> Result<f64, Box<dyn std::error::Error>> {
+    match price {
+        Value::Null => Err(TokenServiceError::PriceUnavailable {
+            mint: mint.to_string(),
+        }
+        .into()),
+        Value::String(price) => Ok(f64::from_str(price)?),
+        other => Err(format!("Unexpected price value for {}: {}", mint, other).into()),
+    }
+}
+
+/// One field whose value differs between two `TokenMetadata` snapshots.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub old: Value,
+    pub new: Value,
+}
+
+/// A field change from `TokenService::metadata_diff`, dated by when the new
+/// value was first cached.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetadataChange {
+    pub changed_at: i64,
+    pub field: String,
+    pub old: Value,
+    pub new: Value,
+}
+
+impl TokenMetadata {
+    /// Field-by-field differences from `self` to `other`, by serialized name
+    /// and in sorted order. The cache `version` is not compared.
+    pub fn diff(&self, other: &TokenMetadata) -> Result<Vec<FieldChange>, serde_json::Error> {
+        Ok(diff_fields(
+            serde_json::to_value(self)?,
+            serde_json::to_value(other)?,
+        ))
+    }
+}
+
+/// Differences between two serialized `TokenMetadata` objects; a non-object
+/// `old` counts as having every field null.
+fn diff_fields(old: Value, new: Value) -> Vec<FieldChange> {
+    let Value::Object(new) = new else {
+        return Vec::new();
+    };
+    let mut old = match old {
+        Value::Object(old) => old,
+        _ => serde_json::Map::new(),
+    };
+
+    let mut changes: Vec<FieldChange> = new
+        .into_iter()
+        .filter(|(field, _)| field != "version")
+        .filter_map(|(field, new)| {
+            let old = old.remove(&field).unwrap_or(Value::Null);
+            (old != new).then_some(FieldChange { field, old, new })
+        })
+        .collect();
+    changes.sort_by(|a, b| a.field.cmp(&b.field));
+    changes
+}
+
+/// Outcome of `TokenService::reconcile`, keyed by canonical mint.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ReconcileReport {
+    /// Mints whose cached entry was rewritten, with what changed. A mint that
+    /// had no usable cache entry lists every field with `old: null`.
+    pub changed: HashMap<String, Vec<FieldChange>>,
+    pub unchanged: Vec<String>,
+    /// Mints that could not be fetched or decoded, with the error.
+    pub failed: HashMap<String, String>,
+}
+
+/// Outcome of `TokenService::refresh_all_prices`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RefreshReport {
+    pub refreshed: usize,
+    /// Mints the price API returned no price for, whose batch failed, or
+    /// whose price the oracle check rejected.
+    pub failed: usize,
+}
+
+/// One of a mint's largest token accounts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HolderEntry {
+    pub token_account: String,
+    /// Wallet or program account that owns the token account.
+    pub owner: String,
+    /// Raw token amount.
+    pub amount: u64,
+    pub ui_amount: String,
+    /// Share of the total supply, in percent.
+    pub percentage: f64,
+    /// Known pool, bonding curve or burn address, when recognised.
+    pub label: Option<String>,
+}
+
+/// One result produced by `TokenService::lookup`.
+#[derive(Debug, Serialize, Deserialize)]
+pub enum LookupEvent {
+    Metadata(Box<TokenMetadata>),
+    /// USD price, served from the cache when fresh.
+    Price {
+        mint: String,
+        price: f64,
+    },
+}
+
+/// Metadata and USD price of a mint fetched together.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TokenSnapshot {
+    pub metadata: TokenMetadata,
+    pub price: Option<f64>,
+    /// Why `price` is missing, when the price leg failed.
+    pub price_error: Option<String>,
+}
+
+/// Staleness buckets reported by `TokenService::cache_age_histogram`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AgeBucket {
+    UnderOneMinute,
+    OneToTenMinutes,
+    TenMinutesToOneHour,
+    OverOneHour,
+}
+
+impl AgeBucket {
+    pub const ALL: [AgeBucket; 4] = [
+        AgeBucket::UnderOneMinute,
+        AgeBucket::OneToTenMinutes,
+        AgeBucket::TenMinutesToOneHour,
+        AgeBucket::OverOneHour,
+    ];
+}
+
+/// How batch APIs treat a failure on one of their items.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BatchMode {
+    /// Return the first error and skip any requests not yet issued.
+    FailFast,
+    /// Log failures and return whatever succeeded.
+    #[default]
+    BestEffort,
+}
+
+/// Orders a price against a threshold, counting values within a few ulps of
+/// each other as equal so a threshold parsed from the same decimal string as
+/// the price never triggers either way.
+fn compare_price(price: f64, threshold: f64) -> std::cmp::Ordering {
+    let tolerance = 4.0 * f64::EPSILON * price.abs().max(threshold.abs());
+    if (price - threshold).abs() <= tolerance {
+        std::cmp::Ordering::Equal
+    } else {
+        price.total_cmp(&threshold)
+    }
+}
+
+/// `Some` when the RPC node rejected the call for lagging behind the cluster,
+/// carrying the slot lag when the node reported it.
+fn node_behind_slots(error: &ClientError) -> Option<Option<u64>> {
+    match error.kind() {
+        ClientErrorKind::RpcError(RpcError::RpcResponseError { code, data, .. })
+            if *code == JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY =>
+        {
+            Some(match data {
+                RpcResponseErrorData::NodeUnhealthy { num_slots_behind } => *num_slots_behind,
+                _ => None,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// `Timeout` for an error that came from a phase's deadline anywhere in its
+/// `source()` chain: a timed-out RPC request, a Postgres connect timeout or a
+/// statement cancelled by `statement_timeout`, or a timed-out API request.
+/// `None` for anything else.
+fn as_timeout(
+    error: &(dyn std::error::Error + 'static),
+    timeouts: &TimeoutConfig,
+) -> Option<TokenServiceError> {
+    std::iter::successors(Some(error), |error| error.source())
+        .find_map(|error| phase_timeout(error, timeouts))
+}
+
+fn phase_timeout(
+    error: &(dyn std::error::Error + 'static),
+    timeouts: &TimeoutConfig,
+) -> Option<TokenServiceError> {
+    let (phase, secs) = if let Some(error) = error.downcast_ref::<ClientError>() {
+        matches!(error.kind(), ClientErrorKind::Reqwest(e) if e.is_timeout())
+            .then_some(("rpc", timeouts.rpc_secs))?
+    } else if let Some(error) = error.downcast_ref::<tokio_postgres::Error>() {
+        let connect_timed_out = std::error::Error::source(error)
+            .and_then(|source| source.downcast_ref::<std::io::Error>())
+            .is_some_and(|source| source.kind() == std::io::ErrorKind::TimedOut);
+        (connect_timed_out || error.code() == Some(&SqlState::QUERY_CANCELED))
+            .then_some(("db", timeouts.db_secs))?
+    } else {
+        let error = error.downcast_ref::<reqwest::Error>()?;
+        error.is_timeout().then_some(("http", timeouts.http_secs))?
+    };
+    Some(TokenServiceError::Timeout {
+        phase,
+        elapsed: Duration::from_secs(secs),
+    })
+}
+
+/// Whether `error` is `AccountNotFound`, as opposed to a failed read.
+fn account_not_found(error: &(dyn std::error::Error + 'static)) -> bool {
+    matches!(
+        TokenServiceError::cause(error),
+        Some(TokenServiceError::AccountNotFound { .. })
+    )
+}
+
+/// Balance of a token account holding `mint`; `None` when the data is not a
+/// token account or belongs to another mint.
+fn token_account_balance(data: &[u8], mint: &Pubkey) -> Option<u64> {
+    let state = StateWithExtensions::<TokenAccount>::unpack(data).ok()?;
+    (state.base.mint == *mint).then_some(state.base.amount)
+}
+
+/// Time elapsed since the unix timestamp `first_seen`.
+fn age_since(first_seen: i64) -> Result<Duration, Box<dyn std::error::Error>> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs() as i64;
+    Ok(Duration::from_secs(
+        now.saturating_sub(first_seen).max(0) as u64
+    ))
+}
+
+/// `raw_supply` scaled by `decimals`, times `price`. The whole-token and
+/// fractional parts are split in integer arithmetic first, so a near-`u64::MAX`
+/// supply neither overflows the scale nor rounds away its fraction. `None`
+/// only when the result is not a finite `f64`.
+fn market_cap(raw_supply: u64, decimals: u8, price: f64) -> Option<f64> {
+    let (whole, fraction) = match 10u128.checked_pow(decimals as u32) {
+        Some(scale) => (
+            (raw_supply as u128 / scale) as f64,
+            (raw_supply as u128 % scale) as f64 / scale as f64,
+        ),
+        None => (0.0, raw_supply as f64 / 10f64.powi(decimals as i32)),
+    };
+    let cap = whole * price + fraction * price;
+    cap.is_finite().then_some(cap)
+}
+
+/// Metadata account bytes with the Borsh `symbol` string cut back to its
+/// longest valid UTF-8 prefix, or `None` when the symbol is already valid
+/// (or the account is too short to hold one).
+fn repair_symbol(data: &[u8]) -> Option<Vec<u8>> {
+    // key, update authority and mint, then the length-prefixed name.
+    let name_start = 1 + 32 + 32;
+    let string_end = |start: usize| -> Option<usize> {
+        let len = u32::from_le_bytes(data.get(start..start + 4)?.try_into().ok()?) as usize;
+        let end = start + 4 + len;
+        (end <= data.len()).then_some(end)
+    };
+    let symbol_start = string_end(name_start)?;
+    let symbol_end = string_end(symbol_start)?;
+
+    let symbol = &data[symbol_start + 4..symbol_end];
+    let valid = sanitize::utf8_prefix(symbol).as_bytes();
+    if valid.len() == symbol.len() {
+        return None;
+    }
+
+    let mut repaired = Vec::with_capacity(data.len());
+    repaired.extend_from_slice(&data[..symbol_start]);
+    repaired.extend_from_slice(&(valid.len() as u32).to_le_bytes());
+    repaired.extend_from_slice(valid);
+    repaired.extend_from_slice(&data[symbol_end..]);
+    Some(repaired)
+}
+
+/// `kind` values of `token_negative_cache` rows.
+const NEGATIVE_METADATA: &str = "metadata";
+const NEGATIVE_PRICE: &str = "price";
+
+fn metadata_pda(mint_pubkey: &Pubkey) -> Pubkey {
+    let (metadata_pda, _) = Pubkey::find_program_address(
+        &[
+            b"metadata",
+            programs::TOKEN_METADATA.as_ref(),
+            mint_pubkey.as_ref(),
+        ],
+        &programs::TOKEN_METADATA,
+    );
+    metadata_pda
 }
+
 pub struct TokenService {
+    rpc_url: String,
+    http_proxy: Option<String>,
+    websocket_endpoints: Arc<EndpointPool>,
     rpc_client: RpcClient,
-    db_client: Client,
+    db_client: Arc<Client>,
     http_client: reqwest::Client,
-    price_cache_duration: u64, // seconds
+    rpc_limits: http::RpcLimits,
+    price_permits: Arc<Semaphore>,
+    offchain_permits: Semaphore,
+    price_coalescer: Option<Arc<PriceCoalescer>>,
+    codecs: CacheCodecs,
+    metrics: Metrics,
+    /// Serialises metadata refreshes per mint; entries die with their last user.
+    refresh_locks: Mutex<HashMap<String, Weak<tokio::sync::Mutex<()>>>>,
+    metadata_enabled: bool,
+    always_fresh_mints: HashSet<String>,
+    price_sinks: Vec<Arc<dyn PriceSink>>,
+    price_cache_duration: u64,            // seconds
+    negative_cache_duration: u64,         // seconds
+    slippage_cache_duration: u64,         // seconds
+    reachability_cache_duration: u64,     // seconds
+    excluded_balance_cache_duration: u64, // seconds
+    offchain: OffchainConfig,
+    metadata: MetadataConfig,
+    prices: PriceConfig,
+    tradeability: TradeabilityConfig,
+    timeouts: TimeoutConfig,
+    readiness: ReadinessConfig,
+    /// Flipped by `check_readiness`; background tasks wait for it when
+    /// `readiness.gate_background_tasks` is set.
+    ready: watch::Sender<bool>,
+    /// Live SOL/USD rate once `watch_sol_usd` has started its poller.
+    sol_usd: Mutex<Option<watch::Receiver<f64>>>,
+    shutdown: watch::Sender<bool>,
+    background_tasks: Mutex<Vec<JoinHandle<()>>>,
 }
 
 impl TokenService {
-    pub async fn new(
-        rpc_url: &str,
-        database_url: &str,
-    ) -> Result<Self, Box<dyn std::error::Error>> {
+    pub async fn new(config: &Config) -> Result<Self, Box<dyn std::error::Error>> {
+        let websocket_endpoints = config.websocket_endpoints();
+        if websocket_endpoints.is_empty() {
+            return Err("No WebSocket endpoint configured".into());
+        }
+
         println!("Connecting to RPC...");
-        let rpc_client = RpcClient::new(rpc_url.to_string());
+        let rpc_limits = http::RpcLimits::new(
+            config.rpc_concurrency,
+            Duration::from_secs(config.timeouts.rpc_secs),
+        );
+        let rpc_client =
+            http::rpc_client(&config.rpc_url, config.http_proxy.as_deref(), &rpc_limits)?;
 
-        println!("Connecting to database: {}", database_url);
-        let (db_client, connection) =
-            tokio_postgres::connect(database_url, NoTls)
-                .await
-                .map_err(|e| {
-                    eprintln!("Database connection error: {:?}", e);
-                    e
-                })?;
+        println!("Connecting to database: {}", config.db_url);
+        let mut db_config = tokio_postgres::Config::from_str(&config.db_url)?;
+        if db_config.get_application_name().is_none() {
+            db_config.application_name(&config.db_application_name);
+        }
+        let db_timeout = Duration::from_secs(config.timeouts.db_secs);
+        if db_config.get_connect_timeout().is_none() {
+            db_config.connect_timeout(db_timeout);
+        }
+        let statement_timeout = format!("-c statement_timeout={}", db_timeout.as_millis());
+        let options = match db_config.get_options() {
+            Some(options) => format!("{} {}", options, statement_timeout),
+            None => statement_timeout,
+        };
+        db_config.options(&options);
+        let (db_client, connection) = db_config.connect(NoTls).await.map_err(|e| {
+            eprintln!("Database connection error: {:?}", e);
+            match as_timeout(&e, &config.timeouts) {
+                Some(timeout) => Box::new(timeout) as Box<dyn std::error::Error>,
+                None => e.into(),
+            }
+        })?;
 
         // Spawn the connection handler
         tokio::spawn(async move {
@@ -48,53 +681,509 @@ impl TokenService {
             }
         });
 
+        if config.metadata_enabled {
+            db_client
+                .execute(
+                    "CREATE TABLE IF NOT EXISTS token_metadata (
+                    mint TEXT PRIMARY KEY,
+                    metadata JSONB NOT NULL,
+                    last_updated BIGINT NOT NULL
+                )",
+                    &[],
+                )
+                .await?;
+
+            db_client
+                .execute(
+                    "ALTER TABLE token_metadata ADD COLUMN IF NOT EXISTS risk_level TEXT",
+                    &[],
+                )
+                .await?;
+
+            db_client
+                .execute(
+                    "CREATE TABLE IF NOT EXISTS token_metadata_history (
+                    mint TEXT NOT NULL,
+                    metadata JSONB NOT NULL,
+                    recorded_at BIGINT NOT NULL
+                )",
+                    &[],
+                )
+                .await?;
+
+            db_client
+                .execute(
+                    "CREATE INDEX IF NOT EXISTS token_metadata_history_mint_recorded_at 
+                     ON token_metadata_history (mint, recorded_at)",
+                    &[],
+                )
+                .await?;
+        }
+
+        // Create price cache table
+        db_client
+            .execute(
+                "CREATE TABLE IF NOT EXISTS token_prices (
+                mint TEXT NOT NULL,
+                quote TEXT NOT NULL DEFAULT 'USD',
+                price DOUBLE PRECISION NOT NULL,
+                last_updated BIGINT NOT NULL,
+                PRIMARY KEY (mint, quote)
+            )",
+                &[],
+            )
+            .await?;
+
+        // Older deployments keyed prices by mint alone; widen the key to
+        // (mint, quote) so the same mint can be cached in several currencies.
+        db_client
+            .batch_execute(
+                "ALTER TABLE token_prices ADD COLUMN IF NOT EXISTS quote TEXT NOT NULL DEFAULT 'USD';
+                DO $$
+                BEGIN
+                    IF (SELECT array_length(indkey::int2[], 1) FROM pg_index
+                        WHERE indrelid = 'token_prices'::regclass AND indisprimary) = 1 THEN
+                        ALTER TABLE token_prices DROP CONSTRAINT token_prices_pkey;
+                        ALTER TABLE token_prices ADD PRIMARY KEY (mint, quote);
+                    END IF;
+                END $$;",
+            )
+            .await?;
+
+        db_client
+            .execute(
+                "CREATE TABLE IF NOT EXISTS token_price_history (
+                mint TEXT NOT NULL,
+                price DOUBLE PRECISION NOT NULL,
+                observed_at BIGINT NOT NULL
+            )",
+                &[],
+            )
+            .await?;
+
+        db_client
+            .execute(
+                "CREATE INDEX IF NOT EXISTS token_price_history_mint_observed_at 
+                 ON token_price_history (mint, observed_at)",
+                &[],
+            )
+            .await?;
+
+        if config.metadata_enabled {
+            db_client
+                .execute(
+                    "CREATE TABLE IF NOT EXISTS token_offchain_metadata (
+                    mint TEXT PRIMARY KEY,
+                    metadata JSONB NOT NULL,
+                    resolver TEXT NOT NULL,
+                    last_updated BIGINT NOT NULL
+                )",
+                    &[],
+                )
+                .await?;
+
+            db_client
+                .execute(
+                    "CREATE TABLE IF NOT EXISTS token_offchain_reachability (
+                    mint TEXT PRIMARY KEY,
+                    reachable BOOLEAN NOT NULL,
+                    last_updated BIGINT NOT NULL
+                )",
+                    &[],
+                )
+                .await?;
+        }
+
+        db_client
+            .execute(
+                "CREATE TABLE IF NOT EXISTS token_slippage (
+                mint TEXT NOT NULL,
+                size_bucket INTEGER NOT NULL,
+                slippage DOUBLE PRECISION NOT NULL,
+                last_updated BIGINT NOT NULL,
+                PRIMARY KEY (mint, size_bucket)
+            )",
+                &[],
+            )
+            .await?;
+
+        db_client
+            .execute(
+                "CREATE TABLE IF NOT EXISTS token_negative_cache (
+                mint TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                reason TEXT NOT NULL,
+                last_updated BIGINT NOT NULL,
+                PRIMARY KEY (mint, kind)
+            )",
+                &[],
+            )
+            .await?;
+
         db_client
             .execute(
-                "CREATE TABLE IF NOT EXISTS token_metadata (
+                "CREATE TABLE IF NOT EXISTS token_decimals (
                 mint TEXT PRIMARY KEY,
-                metadata JSONB NOT NULL,
+                decimals SMALLINT NOT NULL,
                 last_updated BIGINT NOT NULL
             )",
                 &[],
             )
             .await?;
 
-        // Create price cache table
         db_client
             .execute(
-                "CREATE TABLE IF NOT EXISTS token_prices (
+                "CREATE TABLE IF NOT EXISTS token_first_seen (
                 mint TEXT PRIMARY KEY,
-                price DOUBLE PRECISION NOT NULL,
-                last_updated BIGINT NOT NULL
+                first_seen BIGINT NOT NULL
+            )",
+                &[],
+            )
+            .await?;
+
+        // Raw amounts are u64 and may not fit BIGINT, hence TEXT.
+        db_client
+            .execute(
+                "CREATE TABLE IF NOT EXISTS token_excluded_balances (
+                mint TEXT NOT NULL,
+                address TEXT NOT NULL,
+                amount TEXT NOT NULL,
+                last_updated BIGINT NOT NULL,
+                PRIMARY KEY (mint, address)
+            )",
+                &[],
+            )
+            .await?;
+
+        db_client
+            .execute(
+                "CREATE TABLE IF NOT EXISTS dca_events (
+                signature TEXT PRIMARY KEY,
+                slot BIGINT NOT NULL,
+                kind TEXT NOT NULL,
+                payload JSONB NOT NULL,
+                recorded_at BIGINT NOT NULL
             )",
                 &[],
             )
             .await?;
 
+        db_client
+            .execute(
+                "CREATE INDEX IF NOT EXISTS dca_events_slot_idx ON dca_events (slot)",
+                &[],
+            )
+            .await?;
+
+        let db_client = Arc::new(db_client);
+        let always_fresh_mints = config
+            .always_fresh_mints
+            .iter()
+            .map(|mint| canonical_mint(mint))
+            .collect::<Result<HashSet<_>, _>>()?;
+        let http_client = http::build_http_client(
+            config.http_proxy.as_deref(),
+            Duration::from_secs(config.timeouts.http_secs),
+        )?;
+        let price_permits = Arc::new(Semaphore::new(config.price_concurrency.max(1)));
+        let price_coalescer = (config.prices.coalesce_window_ms > 0).then(|| {
+            PriceCoalescer::new(
+                http_client.clone(),
+                price_permits.clone(),
+                JUPITER_PRICE_URL,
+                Duration::from_millis(config.prices.coalesce_window_ms),
+            )
+        });
+
         Ok(Self {
+            rpc_url: config.rpc_url.clone(),
+            http_proxy: config.http_proxy.clone(),
+            websocket_endpoints: Arc::new(EndpointPool::new(
+                websocket_endpoints,
+                config.websocket_failover_after,
+            )),
             rpc_client,
+            price_sinks: vec![Arc::new(PostgresHistorySink::new(
+                db_client.clone(),
+                config.prices.history_sample_interval_secs,
+            ))],
             db_client,
-            http_client: reqwest::Client::new(),
+            http_client,
+            rpc_limits,
+            price_permits,
+            offchain_permits: Semaphore::new(config.offchain_concurrency.max(1)),
+            price_coalescer,
+            metadata_enabled: config.metadata_enabled,
+            always_fresh_mints,
+            metrics: Metrics::default(),
+            refresh_locks: Mutex::new(HashMap::new()),
+            codecs: CacheCodecs::from_key(config.cache_encryption_key.as_deref())?,
             price_cache_duration: 60,
+            negative_cache_duration: config.negative_cache_ttl_secs,
+            slippage_cache_duration: 30,
+            reachability_cache_duration: 300,
+            excluded_balance_cache_duration: 60,
+            offchain: config.offchain.clone(),
+            metadata: config.metadata.clone(),
+            prices: config.prices.clone(),
+            tradeability: config.tradeability.clone(),
+            timeouts: config.timeouts.clone(),
+            readiness: config.readiness.clone(),
+            ready: watch::channel(false).0,
+            sol_usd: Mutex::new(None),
+            shutdown: watch::channel(false).0,
+            background_tasks: Mutex::new(Vec::new()),
         })
     }
 
+    pub fn metadata_enabled(&self) -> bool {
+        self.metadata_enabled
+    }
+
+    /// `TokenServiceError::context`, reporting a deadline hit in any phase
+    /// as `Timeout` with that phase's configured limit.
+    fn context(
+        &self,
+        operation: &'static str,
+        mint: &str,
+    ) -> impl FnOnce(Box<dyn std::error::Error>) -> Box<dyn std::error::Error> + '_ {
+        let context = TokenServiceError::context(operation, mint);
+        move |error| match as_timeout(error.as_ref(), &self.timeouts) {
+            Some(timeout) => context(timeout.into()),
+            None => context(error),
+        }
+    }
+
+    /// Awaits `body` with `context(operation, mint)` applied to its error,
+    /// for entry points whose work is not split into a separate method.
+    async fn in_context<T>(
+        &self,
+        operation: &'static str,
+        mint: &str,
+        body: impl std::future::Future<Output = Result<T, Box<dyn std::error::Error>>>,
+    ) -> Result<T, Box<dyn std::error::Error>> {
+        body.await.map_err(self.context(operation, mint))
+    }
+
+    fn ensure_metadata_enabled(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.metadata_enabled {
+            Ok(())
+        } else {
+            Err(TokenServiceError::Disabled {
+                subsystem: "Metadata",
+            }
+            .into())
+        }
+    }
+
     pub async fn get_metadata(
         &self,
         mint: &str,
     ) -> Result<TokenMetadata, Box<dyn std::error::Error>> {
+        self.load_metadata(mint, false)
+            .await
+            .map_err(self.context("get_metadata", mint))
+    }
+
+    /// Fetches metadata from chain regardless of the cache and rewrites the
+    /// cached entry. A metadata PDA that is not readable yet is retried a
+    /// few times before `MetadataNotFound` is returned, since fresh tokens
+    /// often lag on the RPC node.
+    pub async fn refresh_metadata(
+        &self,
+        mint: &str,
+    ) -> Result<TokenMetadata, Box<dyn std::error::Error>> {
+        self.load_metadata(mint, true)
+            .await
+            .map_err(self.context("refresh_metadata", mint))
+    }
+
+    async fn load_metadata(
+        &self,
+        mint: &str,
+        force: bool,
+    ) -> Result<TokenMetadata, Box<dyn std::error::Error>> {
+        self.ensure_metadata_enabled()?;
+        let mint = &canonical_mint(mint)?;
+
+        let use_cache = !force && !self.always_fresh_mints.contains(mint);
+
         // Check cache first
-        if let Some(metadata) = self.get_from_cache(mint).await? {
-            return Ok(metadata);
+        if use_cache {
+            if let Some(metadata) = self.get_from_cache(mint).await? {
+                self.metrics.cache_hit(&self.metrics.metadata_cache_hits);
+                return Ok(metadata);
+            }
+        }
+        self.metrics.cache_miss(&self.metrics.metadata_cache_misses);
+
+        // Only one refresh per mint at a time; whoever waited on the lock
+        // picks up the row the previous holder wrote.
+        let refresh_lock = self.refresh_lock(mint);
+        let _guard = refresh_lock.lock().await;
+        if use_cache {
+            if let Some(metadata) = self.get_from_cache(mint).await? {
+                return Ok(metadata);
+            }
+            if self.get_negative(mint, NEGATIVE_METADATA).await?.is_some() {
+                return Err(TokenServiceError::MetadataNotFound {
+                    mint: mint.to_string(),
+                }
+                .into());
+            }
         }
 
         // If not in cache or expired, fetch from chain
-        let metadata = self.fetch_token_metadata(mint).await?;
+        let pda_retries = if force {
+            self.metadata.missing_pda_retries
+        } else {
+            0
+        };
+        let metadata = match self.fetch_token_metadata(mint, pda_retries).await {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                Metrics::increment(&self.metrics.metadata_fetch_errors);
+                if let Some(TokenServiceError::MetadataNotFound { .. }) =
+                    TokenServiceError::cause(e.as_ref())
+                {
+                    self.save_negative(mint, NEGATIVE_METADATA, "not_found")
+                        .await?;
+                }
+                return Err(e);
+            }
+        };
         self.save_to_cache(&metadata).await?;
+        self.clear_negative(mint, NEGATIVE_METADATA).await?;
 
         Ok(metadata)
     }
 
+    fn refresh_lock(&self, mint: &str) -> Arc<tokio::sync::Mutex<()>> {
+        let mut locks = self.refresh_locks.lock().unwrap();
+        if let Some(lock) = locks.get(mint).and_then(Weak::upgrade) {
+            return lock;
+        }
+
+        locks.retain(|_, lock| lock.strong_count() > 0);
+        let lock = Arc::new(tokio::sync::Mutex::new(()));
+        locks.insert(mint.to_string(), Arc::downgrade(&lock));
+        lock
+    }
+
+    /// Re-reads `mints` from chain in batches, compares each against its
+    /// cached entry and rewrites only the entries that changed. Under
+    /// `BatchMode::BestEffort` a batch that cannot be read has its mints
+    /// reported as failed and the rest go on.
+    pub async fn reconcile(
+        &self,
+        mints: &[&str],
+        mode: BatchMode,
+    ) -> Result<ReconcileReport, Box<dyn std::error::Error>> {
+        self.in_context("reconcile", "", async {
+            self.ensure_metadata_enabled()?;
+            let mut mints = mints
+                .iter()
+                .map(|mint| canonical_mint(mint))
+                .collect::<Result<Vec<_>, _>>()?;
+            mints.sort();
+            mints.dedup();
+
+            let mut report = ReconcileReport::default();
+            // Two accounts per mint: the mint itself and its metadata PDA.
+            for chunk in mints.chunks(MULTIPLE_ACCOUNTS_LIMIT / 2) {
+                let mut keys = Vec::with_capacity(chunk.len() * 2);
+                for mint in chunk {
+                    let mint_pubkey = Pubkey::from_str(mint)?;
+                    keys.push(mint_pubkey);
+                    keys.push(metadata_pda(&mint_pubkey));
+                }
+                let accounts = self
+                    .get_multiple_decoded(&keys, |account| Ok(account.data.clone()))
+                    .await;
+
+                let mut accounts = accounts.into_iter();
+                for mint in chunk {
+                    let (Some(mint_read), Some(metadata_read)) = (accounts.next(), accounts.next())
+                    else {
+                        break;
+                    };
+                    let fresh = match (mint_read, metadata_read) {
+                        (Ok(mint_data), Ok(metadata_data)) => self
+                            .decode_metadata_account(mint, &metadata_data)
+                            .and_then(|(metadata, symbol_cut)| {
+                                self.build_metadata(mint, &mint_data, metadata, symbol_cut)
+                            }),
+                        (Ok(_), Err(e)) if account_not_found(e.as_ref()) => {
+                            Err(TokenServiceError::MetadataNotFound { mint: mint.clone() }.into())
+                        }
+                        (Err(e), _) if account_not_found(e.as_ref()) => {
+                            self.fetch_compressed_metadata(mint).await
+                        }
+                        (Err(e), _) | (Ok(_), Err(e)) => {
+                            if mode == BatchMode::FailFast {
+                                return Err(e);
+                            }
+                            eprintln!("Reconcile read of {} failed: {}", mint, e);
+                            report.failed.insert(mint.clone(), e.to_string());
+                            continue;
+                        }
+                    };
+                    let fresh = match fresh {
+                        Ok(fresh) => fresh,
+                        Err(e) => {
+                            report.failed.insert(mint.clone(), e.to_string());
+                            continue;
+                        }
+                    };
+
+                    let changes = match self.get_from_cache(mint).await? {
+                        Some(cached) => cached.diff(&fresh)?,
+                        None => diff_fields(Value::Null, serde_json::to_value(&fresh)?),
+                    };
+                    if changes.is_empty() {
+                        report.unchanged.push(mint.clone());
+                    } else {
+                        self.save_to_cache(&fresh).await?;
+                        report.changed.insert(mint.clone(), changes);
+                    }
+                }
+            }
+
+            Ok(report)
+        })
+        .await
+    }
+
+    /// Digest of every cached metadata fingerprint in mint order, so two
+    /// databases can be compared for divergence. Rows are streamed rather
+    /// than loaded at once.
+    pub async fn cache_digest(&self) -> Result<String, Box<dyn std::error::Error>> {
+        self.in_context("cache_digest", "", async {
+            self.ensure_metadata_enabled()?;
+            let rows = self
+                .db_client
+                .query_raw(
+                    "SELECT mint, metadata FROM token_metadata ORDER BY mint",
+                    std::iter::empty::<i32>(),
+                )
+                .await?;
+            let mut rows = std::pin::pin!(rows);
+
+            let mut hasher = Hasher::default();
+            while let Some(row) = rows.try_next().await? {
+                let mint: String = row.get(0);
+                let metadata: TokenMetadata =
+                    serde_json::from_value(self.codecs.open(row.get(1))?)?;
+                for part in [mint, metadata.fingerprint()] {
+                    hasher.hash(&(part.len() as u64).to_le_bytes());
+                    hasher.hash(part.as_bytes());
+                }
+            }
+            Ok(hasher.result().to_string())
+        })
+        .await
+    }
+
     async fn get_from_cache(
         &self,
         mint: &str,
@@ -108,120 +1197,2953 @@ impl TokenService {
             .await?;
 
         Ok(match row {
-            Some(row) => Some(serde_json::from_value(row.get(0))?),
+            Some(row) => {
+                let metadata: TokenMetadata =
+                    serde_json::from_value(self.codecs.open(row.get(0))?)?;
+                (metadata.version >= METADATA_VERSION).then_some(metadata)
+            }
             None => None,
         })
     }
 
+    /// Most recently cached tokens whose persisted risk level is `level`.
+    pub async fn list_by_risk(
+        &self,
+        level: RiskLevel,
+        limit: usize,
+    ) -> Result<Vec<TokenMetadata>, Box<dyn std::error::Error>> {
+        self.in_context("list_by_risk", "", async {
+            self.ensure_metadata_enabled()?;
+            let rows = self
+                .db_client
+                .query(
+                    "SELECT metadata FROM token_metadata 
+                     WHERE risk_level = $1 ORDER BY last_updated DESC LIMIT $2",
+                    &[&level.as_str(), &(limit as i64)],
+                )
+                .await?;
+
+            rows.into_iter()
+                .map(|row| Ok(serde_json::from_value(self.codecs.open(row.get(0))?)?))
+                .collect()
+        })
+        .await
+    }
+
     async fn save_to_cache(
         &self,
         metadata: &TokenMetadata,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let json = serde_json::to_value(metadata)?;
+        let json = self.codecs.seal(serde_json::to_value(metadata)?)?;
         let now = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)?
             .as_secs() as i64;
 
+        let risk_level = RiskLevel::assess(metadata).as_str();
+
+        self.record_metadata_version(metadata, &json, now).await?;
         self.db_client
             .execute(
-                "INSERT INTO token_metadata (mint, metadata, last_updated) 
-                 VALUES ($1, $2, $3)
-                 ON CONFLICT (mint) DO UPDATE SET metadata = $2, last_updated = $3",
-                &[&metadata.mint, &json, &now],
+                "INSERT INTO token_metadata (mint, metadata, last_updated, risk_level) 
+                 VALUES ($1, $2, $3, $4)
+                 ON CONFLICT (mint) DO UPDATE SET metadata = $2, last_updated = $3, risk_level = $4",
+                &[&metadata.mint, &json, &now, &risk_level],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Appends `metadata` to the history when it differs from the cached
+    /// row. A cached row from before the history existed is recorded first,
+    /// dated by its last refresh, so the first change still has an old side.
+    async fn record_metadata_version(
+        &self,
+        metadata: &TokenMetadata,
+        sealed: &Value,
+        now: i64,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let previous = self
+            .db_client
+            .query_opt(
+                "SELECT metadata, last_updated, 
+                 EXISTS (SELECT 1 FROM token_metadata_history WHERE mint = $1) 
+                 FROM token_metadata WHERE mint = $1",
+                &[&metadata.mint],
+            )
+            .await?;
+
+        if let Some(row) = previous {
+            let old = self.codecs.open(row.get(0))?;
+            if diff_fields(old, serde_json::to_value(metadata)?).is_empty() {
+                return Ok(());
+            }
+            let has_history: bool = row.get(2);
+            if !has_history {
+                let last_updated: i64 = row.get(1);
+                let old_sealed: Value = row.get(0);
+                self.db_client
+                    .execute(
+                        "INSERT INTO token_metadata_history (mint, metadata, recorded_at) 
+                         VALUES ($1, $2, $3)",
+                        &[&metadata.mint, &old_sealed, &last_updated],
+                    )
+                    .await?;
+            }
+        }
+
+        self.db_client
+            .execute(
+                "INSERT INTO token_metadata_history (mint, metadata, recorded_at) 
+                 VALUES ($1, $2, $3)",
+                &[&metadata.mint, sealed, &now],
             )
             .await?;
         Ok(())
     }
 
+    /// Field changes the mint's cached metadata went through after `since`
+    /// (Unix seconds), oldest first, relative to the version cached at
+    /// `since` or, failing that, the oldest one recorded.
+    pub async fn metadata_diff(
+        &self,
+        mint: &str,
+        since: i64,
+    ) -> Result<Vec<MetadataChange>, Box<dyn std::error::Error>> {
+        self.in_context("metadata_diff", mint, async {
+            self.ensure_metadata_enabled()?;
+            let mint = &canonical_mint(mint)?;
+            let rows = self
+                .db_client
+                .query(
+                    "SELECT metadata, recorded_at FROM token_metadata_history 
+                     WHERE mint = $1 ORDER BY recorded_at",
+                    &[mint],
+                )
+                .await?;
+            let versions = rows
+                .iter()
+                .map(|row| Ok((self.codecs.open(row.get(0))?, row.get::<_, i64>(1))))
+                .collect::<Result<Vec<(Value, i64)>, Box<dyn std::error::Error>>>()?;
+
+            let baseline = versions
+                .iter()
+                .rposition(|(_, recorded_at)| *recorded_at <= since)
+                .unwrap_or_default();
+            let mut changes = Vec::new();
+            for pair in versions.get(baseline..).unwrap_or_default().windows(2) {
+                let ((old, _), (new, changed_at)) = (&pair[0], &pair[1]);
+                changes.extend(
+                    diff_fields(old.clone(), new.clone())
+                        .into_iter()
+                        .map(|change| MetadataChange {
+                            changed_at: *changed_at,
+                            field: change.field,
+                            old: change.old,
+                            new: change.new,
+                        }),
+                );
+            }
+            Ok(changes)
+        })
+        .await
+    }
+
     async fn fetch_token_metadata(
         &self,
         mint: &str,
+        pda_retries: u32,
     ) -> Result<TokenMetadata, Box<dyn std::error::Error>> {
         let mint_pubkey = Pubkey::from_str(mint)?;
-        let mint_account = self.rpc_client.get_account(&mint_pubkey)?;
-        let mint_data = Mint::unpack(&mint_account.data)?;
-
-        let (metadata_pda, _) = Pubkey::find_program_address(
-            &[
-                b"metadata",
-                mpl_token_metadata::ID.as_ref(),
-                mint_pubkey.as_ref(),
-            ],
-            &mpl_token_metadata::ID,
-        );
+        let Some(mint_account) = self
+            .retry_node_behind(move || async move {
+                Ok(self
+                    .rpc_client
+                    .get_account_with_commitment(&mint_pubkey, self.rpc_client.commitment())
+                    .await?)
+            })
+            .await?
+            .value
+        else {
+            return self.fetch_compressed_metadata(mint).await;
+        };
+        let delay = Duration::from_millis(self.metadata.missing_pda_retry_delay_ms);
+        let mut attempt = 0;
+        let (metadata, symbol_cut) = loop {
+            match self.fetch_metadata_account(&mint_pubkey).await {
+                Err(e)
+                    if attempt < pda_retries
+                        && matches!(
+                            TokenServiceError::cause(e.as_ref()),
+                            Some(TokenServiceError::MetadataNotFound { .. })
+                        ) =>
+                {
+                    attempt += 1;
+                    eprintln!(
+                        "Metadata account for {} not found yet, retrying ({}/{})",
+                        mint, attempt, pda_retries
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                result => break result?,
+            }
+        };
+        self.build_metadata(mint, &mint_account.data, metadata, symbol_cut)
+    }
 
-        let metadata_account = self.rpc_client.get_account(&metadata_pda)?;
-        let metadata = Metadata::from_bytes(&metadata_account.data)?;
+    /// Assembles `TokenMetadata` from the raw mint account and its decoded
+    /// Metaplex metadata account; `symbol_cut` is set when the symbol had to
+    /// be trimmed back to valid UTF-8.
+    fn build_metadata(
+        &self,
+        mint: &str,
+        mint_account_data: &[u8],
+        metadata: Metadata,
+        symbol_cut: bool,
+    ) -> Result<TokenMetadata, Box<dyn std::error::Error>> {
+        let mint_data = StateWithExtensions::<Mint>::unpack(mint_account_data)?.base;
+        let (symbol, symbol_suspicious) = self.display_symbol(mint, &metadata.symbol);
+        let (name, raw_name) = self.display_name(sanitize::trim_padding(&metadata.name));
+        let (name, symbol, truncated) = self.limit_lengths(name, symbol);
+        let truncated = truncated || (symbol_cut && self.metadata.flag_cut_symbols);
 
         Ok(TokenMetadata {
+            version: METADATA_VERSION,
             mint: mint.to_string(),
-            name: metadata.name.trim_matches(char::from(0)).to_string(),
-            symbol: metadata.symbol.trim_matches(char::from(0)).to_string(),
+            name,
+            raw_name,
+            symbol,
             decimals: mint_data.decimals,
             supply: mint_data.supply,
+            ui_supply: spl_token_2022::amount_to_ui_amount_string_trimmed(
+                mint_data.supply,
+                mint_data.decimals,
+            ),
+            uri: metadata.uri.trim_matches(char::from(0)).to_string(),
+            mint_authority: Option::<Pubkey>::from(mint_data.mint_authority)
+                .map(|key| key.to_string()),
+            freeze_authority: Option::<Pubkey>::from(mint_data.freeze_authority)
+                .map(|key| key.to_string()),
+            update_authority: metadata.update_authority.to_string(),
+            symbol_suspicious,
+            seller_fee_basis_points: metadata.seller_fee_basis_points,
+            is_mutable: metadata.is_mutable,
+            creators_verified: metadata
+                .creators
+                .as_ref()
+                .filter(|creators| !creators.is_empty())
+                .map(|creators| creators.iter().any(|creator| creator.verified)),
+            compressed: false,
+            truncated,
+            decimals_unusual: self.decimals_unusual(mint_data.decimals, mint_data.supply),
+        })
+    }
+
+    /// Reads a compressed NFT through the DAS `getAsset` method, for ids that
+    /// have no mint account. Needs an RPC provider that serves DAS.
+    async fn fetch_compressed_metadata(
+        &self,
+        mint: &str,
+    ) -> Result<TokenMetadata, Box<dyn std::error::Error>> {
+        let request = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "getAsset",
+            "params": { "id": mint },
+        });
+        let response = self
+            .http_client
+            .post(&self.rpc_url)
+            .json(&request)
+            .send()
+            .await?;
+        let data: Value = response.json().await?;
+
+        let asset = CompressedAsset::from_asset(&data["result"])
+            .ok_or_else(|| format!("Mint account {} not found", mint))?;
+        let (symbol, symbol_suspicious) = self.display_symbol(mint, &asset.symbol);
+        let (name, raw_name) = self.display_name(&asset.name);
+        let (name, symbol, truncated) = self.limit_lengths(name, symbol);
+
+        Ok(TokenMetadata {
+            version: METADATA_VERSION,
+            mint: mint.to_string(),
+            name,
+            raw_name,
+            symbol,
+            decimals: 0,
+            supply: 1,
+            ui_supply: "1".to_string(),
+            uri: asset.uri,
+            mint_authority: None,
+            freeze_authority: None,
+            update_authority: asset.update_authority,
+            symbol_suspicious,
+            seller_fee_basis_points: asset.seller_fee_basis_points,
+            is_mutable: asset.is_mutable,
+            creators_verified: asset.creators_verified,
+            compressed: true,
+            truncated,
+            decimals_unusual: false,
+        })
+    }
+
+    /// Whether a fungible mint's decimals fall outside the configured range.
+    fn decimals_unusual(&self, decimals: u8, supply: u64) -> bool {
+        let is_nft = decimals == 0 && supply == 1;
+        !is_nft && !(self.metadata.min_decimals..=self.metadata.max_decimals).contains(&decimals)
+    }
+
+    /// Applies the configured name and symbol length limits, returning
+    /// whether either was cut.
+    fn limit_lengths(&self, name: String, symbol: String) -> (String, String, bool) {
+        let short_name = sanitize::truncate_chars(&name, self.metadata.max_name_length);
+        let short_symbol = sanitize::truncate_chars(&symbol, self.metadata.max_symbol_length);
+        let truncated = short_name.is_some() || short_symbol.is_some();
+        (
+            short_name.unwrap_or(name),
+            short_symbol.unwrap_or(symbol),
+            truncated,
+        )
+    }
+
+    /// Returns the name to display and, when it was decoded from a vanity
+    /// encoding, the on-chain value it came from.
+    fn display_name(&self, raw_name: &str) -> (String, Option<String>) {
+        if self.metadata.decode_vanity_names {
+            if let Some(decoded) = sanitize::decode_vanity(raw_name) {
+                return (decoded, Some(raw_name.to_string()));
+            }
+        }
+        (raw_name.to_string(), None)
+    }
+
+    /// Returns the symbol to display and whether the on-chain value looked
+    /// suspicious (non-empty but invisible).
+    fn display_symbol(&self, mint: &str, raw_symbol: &str) -> (String, bool) {
+        let raw_symbol = sanitize::trim_padding(raw_symbol);
+        let symbol_suspicious = !raw_symbol.is_empty() && sanitize::is_blank(raw_symbol);
+        let symbol = if raw_symbol.is_empty()
+            || (symbol_suspicious && self.metadata.blank_symbol_as_missing)
+        {
+            sanitize::short_address(mint)
+        } else {
+            raw_symbol.to_string()
+        };
+        (symbol, symbol_suspicious)
+    }
+
+    async fn fetch_metadata_account(
+        &self,
+        mint_pubkey: &Pubkey,
+    ) -> Result<(Metadata, bool), Box<dyn std::error::Error>> {
+        let metadata_pda = metadata_pda(mint_pubkey);
+
+        let metadata_account = self
+            .retry_node_behind(move || async move {
+                Ok(self
+                    .rpc_client
+                    .get_account_with_commitment(&metadata_pda, self.rpc_client.commitment())
+                    .await?
+                    .value)
+            })
+            .await?
+            .ok_or_else(|| TokenServiceError::MetadataNotFound {
+                mint: mint_pubkey.to_string(),
+            })?;
+        self.decode_metadata_account(&mint_pubkey.to_string(), &metadata_account.data)
+    }
+
+    /// Decodes a Metaplex metadata account read for `mint`, guarding against
+    /// a wrong account coming back (e.g. from an indexer bug). A symbol that
+    /// is not valid UTF-8, typically a multibyte character cut off by the
+    /// fixed-size field, is trimmed to its valid prefix rather than failing
+    /// the whole account; the flag reports whether that happened.
+    fn decode_metadata_account(
+        &self,
+        mint: &str,
+        data: &[u8],
+    ) -> Result<(Metadata, bool), Box<dyn std::error::Error>> {
+        let (metadata, symbol_cut) = match Metadata::from_bytes(data) {
+            Ok(metadata) => (metadata, false),
+            Err(e) => match repair_symbol(data) {
+                Some(repaired) => (Metadata::from_bytes(&repaired)?, true),
+                None => return Err(e.into()),
+            },
+        };
+        let actual = metadata.mint.to_string();
+        if actual != mint {
+            if self.metadata.reject_mint_mismatch {
+                return Err(TokenServiceError::MintMismatch {
+                    expected: mint.to_string(),
+                    actual,
+                }
+                .into());
+            }
+            eprintln!("Metadata fetched for {} belongs to mint {}", mint, actual);
+        }
+        Ok((metadata, symbol_cut))
+    }
+
+    /// Runs an RPC call, retrying it after a short delay while the node
+    /// reports that it is behind the cluster.
+    async fn retry_node_behind<T, F>(
+        &self,
+        mut call: impl FnMut() -> F,
+    ) -> Result<T, Box<dyn std::error::Error>>
+    where
+        F: Future<Output = Result<T, Box<dyn std::error::Error>>>,
+    {
+        let delay = Duration::from_millis(self.metadata.node_behind_retry_delay_ms);
+        let mut attempt = 0;
+        loop {
+            match call().await {
+                Err(e) if attempt < self.metadata.node_behind_retries => {
+                    let Some(lag) = e.downcast_ref::<ClientError>().and_then(node_behind_slots)
+                    else {
+                        return Err(e);
+                    };
+                    attempt += 1;
+                    match lag {
+                        Some(slots) => eprintln!(
+                            "RPC node is {} slots behind, retrying ({}/{})",
+                            slots, attempt, self.metadata.node_behind_retries
+                        ),
+                        None => eprintln!(
+                            "RPC node is behind, retrying ({}/{})",
+                            attempt, self.metadata.node_behind_retries
+                        ),
+                    }
+                    tokio::time::sleep(delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    pub async fn verify_creators(
+        &self,
+        mint: &str,
+        trusted: &[String],
+    ) -> Result<CreatorVerification, Box<dyn std::error::Error>> {
+        self.in_context("verify_creators", mint, async {
+            self.ensure_metadata_enabled()?;
+            let mint_pubkey = Pubkey::from_str(mint)?;
+            let (metadata, _) = self.fetch_metadata_account(&mint_pubkey).await?;
+
+            let creators = metadata
+                .creators
+                .unwrap_or_default()
+                .into_iter()
+                .map(|creator| CreatorStatus {
+                    address: creator.address.to_string(),
+                    share: creator.share,
+                    verified: creator.verified,
+                    trusted: trusted
+                        .iter()
+                        .any(|t| Pubkey::from_str(t).is_ok_and(|t| t == creator.address)),
+                })
+                .collect();
+
+            Ok(CreatorVerification {
+                mint: mint.to_string(),
+                creators,
+            })
+        })
+        .await
+    }
+
+    pub async fn get_offchain_metadata(
+        &self,
+        mint: &str,
+    ) -> Result<OffchainMetadata, Box<dyn std::error::Error>> {
+        self.in_context("get_offchain_metadata", mint, async {
+            self.ensure_metadata_enabled()?;
+            let mint = &canonical_mint(mint)?;
+
+            if let Some(metadata) = self.get_offchain_from_cache(mint).await? {
+                return Ok(metadata);
+            }
+
+            let mint_pubkey = Pubkey::from_str(mint)?;
+            let uri = self.fetch_metadata_account(&mint_pubkey).await?.0.uri;
+            let uri = uri.trim_matches(char::from(0)).trim();
+            if uri.is_empty() {
+                return Err(format!("Token {} has no off-chain metadata URI", mint).into());
+            }
+
+            let metadata = self.resolve_offchain(uri).await?;
+            self.save_offchain_to_cache(mint, &metadata).await?;
+
+            Ok(metadata)
+        })
+        .await
+    }
+
+    /// Fetches and parses off-chain metadata JSON from a URI the caller
+    /// already has, with the same gateway fallback and size limit as the
+    /// mint lookup. Nothing is cached, as there is no mint to key it by.
+    pub async fn parse_offchain_uri(
+        &self,
+        uri: &str,
+    ) -> Result<OffchainMetadata, Box<dyn std::error::Error>> {
+        let uri = uri.trim_matches(char::from(0)).trim();
+        if uri.is_empty() {
+            return Err("Off-chain metadata URI is empty".into());
+        }
+        self.resolve_offchain(uri).await
+    }
+
+    /// Website and social links from the token's off-chain metadata, served
+    /// from the off-chain metadata cache when present.
+    pub async fn get_links(&self, mint: &str) -> Result<TokenLinks, Box<dyn std::error::Error>> {
+        self.in_context("get_links", mint, async {
+            let offchain = self.get_offchain_metadata(mint).await?;
+            Ok(TokenLinks::from_json(&offchain.json))
         })
+        .await
+    }
+
+    /// Fetches off-chain JSON from the URI, falling back to the configured
+    /// IPFS gateways when the primary location 404s, times out or is not
+    /// directly fetchable.
+    async fn resolve_offchain(
+        &self,
+        uri: &str,
+    ) -> Result<OffchainMetadata, Box<dyn std::error::Error>> {
+        let candidates = offchain::candidate_urls(uri, &self.offchain.gateways);
+        if candidates.is_empty() {
+            return Err(format!("Unsupported off-chain metadata URI: {}", uri).into());
+        }
+
+        let mut failures = Vec::new();
+        for (resolver, url) in candidates {
+            match self.fetch_offchain_json(&url).await {
+                Ok(json) => return Ok(OffchainMetadata::from_json(uri, &resolver, json)),
+                Err(e) => {
+                    eprintln!("Off-chain fetch via {} failed: {}", resolver, e);
+                    failures.push(format!("{}: {}", resolver, e));
+                }
+            }
+        }
+
+        Err(format!("All resolvers failed for {}: {}", uri, failures.join("; ")).into())
+    }
+
+    async fn fetch_offchain_json(&self, url: &str) -> Result<Value, Box<dyn std::error::Error>> {
+        let _permit = self.offchain_permits.acquire().await?;
+        let mut response = self
+            .http_client
+            .get(url)
+            .timeout(Duration::from_secs(self.offchain.timeout_secs))
+            .send()
+            .await?
+            .error_for_status()?;
+
+        let limit = self.offchain.max_json_bytes;
+        let too_large = || format!("Off-chain JSON at {} exceeds {} bytes", url, limit);
+        if response
+            .content_length()
+            .is_some_and(|length| length > limit as u64)
+        {
+            return Err(too_large().into());
+        }
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            if body.len() + chunk.len() > limit {
+                return Err(too_large().into());
+            }
+            body.extend_from_slice(&chunk);
+        }
+        Ok(serde_json::from_slice(&body)?)
+    }
+
+    async fn get_offchain_from_cache(
+        &self,
+        mint: &str,
+    ) -> Result<Option<OffchainMetadata>, Box<dyn std::error::Error>> {
+        let row = self
+            .db_client
+            .query_opt(
+                "SELECT metadata FROM token_offchain_metadata WHERE mint = $1",
+                &[&mint],
+            )
+            .await?;
+
+        Ok(match row {
+            Some(row) => Some(serde_json::from_value(self.codecs.open(row.get(0))?)?),
+            None => None,
+        })
+    }
+
+    async fn save_offchain_to_cache(
+        &self,
+        mint: &str,
+        metadata: &OffchainMetadata,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let json = self.codecs.seal(serde_json::to_value(metadata)?)?;
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs() as i64;
+
+        self.db_client
+            .execute(
+                "INSERT INTO token_offchain_metadata (mint, metadata, resolver, last_updated) 
+                 VALUES ($1, $2, $3, $4)
+                 ON CONFLICT (mint) DO UPDATE SET metadata = $2, resolver = $3, last_updated = $4",
+                &[&mint, &json, &metadata.resolver, &now],
+            )
+            .await?;
+        Ok(())
     }
 
     pub async fn fetch_mint_price(&self, mint: &str) -> Result<f64, Box<dyn std::error::Error>> {
-        let url = format!("https://api.jup.ag/price/v2?ids={}", mint);
+        self.in_context("fetch_mint_price", mint, async {
+            let mint = &canonical_mint(mint)?;
+            let entry = self.fetch_price_entry(mint, false).await?;
+            parse_price(mint, &entry["price"])
+        })
+        .await
+    }
+
+    async fn fetch_price_entry(
+        &self,
+        mint: &str,
+        extra_info: bool,
+    ) -> Result<Value, Box<dyn std::error::Error>> {
+        let entry = self
+            .request_price_entry(mint, extra_info)
+            .await
+            .and_then(|entry| {
+                entry.ok_or_else(|| {
+                    TokenServiceError::PriceMissing {
+                        mint: mint.to_string(),
+                    }
+                    .into()
+                })
+            });
+        if entry.is_err() {
+            Metrics::increment(&self.metrics.price_fetch_errors);
+        }
+        entry
+    }
+
+    async fn request_price_entry(
+        &self,
+        mint: &str,
+        extra_info: bool,
+    ) -> Result<Option<Value>, Box<dyn std::error::Error>> {
+        if let Some(coalescer) = self.price_coalescer.as_ref().filter(|_| !extra_info) {
+            return coalescer.fetch_entry(mint).await;
+        }
+
+        let mut url = format!("{}?ids={}", JUPITER_PRICE_URL, mint);
+        if extra_info {
+            url.push_str("&showExtraInfo=true");
+        }
+        let _permit = self.price_permits.acquire().await?;
         let response = self.http_client.get(&url).send().await?;
-        let data: Value = response.json().await?;
-        let price = f64::from_str(&data["data"][mint]["price"].as_str().unwrap_or("0.0"))?;
-        Ok(price)
+        let mut data: Value = response.json().await?;
+        Ok(data["data"].get_mut(mint).map(Value::take))
+    }
+
+    /// Fetches a fresh USD price with its confidence tier, bypassing the
+    /// cache. The price still refreshes the cache and history.
+    pub async fn get_price_detailed(
+        &self,
+        mint: &str,
+    ) -> Result<PriceDetail, Box<dyn std::error::Error>> {
+        self.in_context("get_price_detailed", mint, async {
+            let mint = &canonical_mint(mint)?;
+            let entry = self
+                .fetch_price_entry(mint, self.prices.show_extra_info)
+                .await?;
+            let detail = PriceDetail::from_entry(mint, &entry)?;
+            self.check_oracle(mint, detail.price).await?;
+
+            self.record_price_history(mint, detail.price).await?;
+            self.save_price_to_cache(mint, USD, detail.price).await?;
+            Ok(detail)
+        })
+        .await
     }
 
     pub async fn get_price(&self, mint: &str) -> Result<f64, Box<dyn std::error::Error>> {
+        self.price_in(mint, USD)
+            .await
+            .map_err(self.context("get_price", mint))
+    }
+
+    /// `get_price`, falling back to the latest recorded price when the cache
+    /// has nothing fresh and the price API cannot be reached, as long as that
+    /// record is within `max_last_resort_age_secs`. A price the API reports
+    /// as unavailable or missing is a definitive answer and not papered over.
+    pub async fn get_price_or_last_resort(
+        &self,
+        mint: &str,
+    ) -> Result<PriceEstimate, Box<dyn std::error::Error>> {
+        self.in_context("get_price_or_last_resort", mint, async {
+            let error = match self.get_price(mint).await {
+                Ok(price) => return Ok(PriceEstimate::Live(price)),
+                Err(e) => e,
+            };
+            if self.prices.max_last_resort_age_secs == 0
+                || matches!(
+                    TokenServiceError::cause(error.as_ref()),
+                    Some(TokenServiceError::PriceUnavailable { .. })
+                        | Some(TokenServiceError::PriceMissing { .. })
+                )
+            {
+                return Err(error);
+            }
+
+            let mint = &canonical_mint(mint)?;
+            let row = self
+                .db_client
+                .query_opt(
+                    "SELECT price, observed_at FROM token_price_history 
+                     WHERE mint = $1 AND observed_at > $2 
+                     ORDER BY observed_at DESC LIMIT 1",
+                    &[
+                        &mint,
+                        &(std::time::SystemTime::now()
+                            .duration_since(std::time::UNIX_EPOCH)?
+                            .as_secs() as i64
+                            - self.prices.max_last_resort_age_secs as i64),
+                    ],
+                )
+                .await?;
+            match row {
+                Some(row) => {
+                    eprintln!("Using last-resort price for {}: {}", mint, error);
+                    Ok(PriceEstimate::LastResort {
+                        price: row.get(0),
+                        observed_at: row.get(1),
+                    })
+                }
+                None => Err(error),
+            }
+        })
+        .await
+    }
+
+    /// Returns the mint's price in `quote`. Non-USD quotes are derived from the
+    /// USD price and the USD price of the quote's configured reference mint.
+    pub async fn get_price_in(
+        &self,
+        mint: &str,
+        quote: &str,
+    ) -> Result<f64, Box<dyn std::error::Error>> {
+        self.price_in(mint, quote)
+            .await
+            .map_err(self.context("get_price_in", mint))
+    }
+
+    async fn price_in(&self, mint: &str, quote: &str) -> Result<f64, Box<dyn std::error::Error>> {
+        let quote = quote.to_uppercase();
+        let mint = &canonical_mint(mint)?;
+
         // Check cache first
-        if let Some(price) = self.get_price_from_cache(mint).await? {
-            return Ok(price);
+        if !self.always_fresh_mints.contains(mint) {
+            if let Some(price) = self.get_price_from_cache(mint, &quote).await? {
+                self.metrics.cache_hit(&self.metrics.price_cache_hits);
+                return Ok(price);
+            }
         }
+        self.metrics.cache_miss(&self.metrics.price_cache_misses);
 
         // If not in cache or expired, fetch from API
-        let price = self.fetch_mint_price(mint).await?;
-        self.save_price_to_cache(mint, price).await?;
+        let price = if quote == USD {
+            let price = self.fetch_usd_price_negative_cached(mint).await?;
+            self.check_oracle(mint, price).await?;
+            self.record_price_history(mint, price).await?;
+            price
+        } else {
+            let reference_mint = self
+                .prices
+                .quote_currencies
+                .get(&quote)
+                .ok_or_else(|| format!("Quote currency {} is not configured", quote))?;
+            let usd_price = Box::pin(self.price_in(mint, USD)).await?;
+            let reference_price = Box::pin(self.get_price_in(reference_mint, USD)).await?;
+            if reference_price <= 0.0 {
+                return Err(format!("Reference price for {} is not positive", quote).into());
+            }
+            usd_price / reference_price
+        };
+        self.save_price_to_cache(mint, &quote, price).await?;
 
         Ok(price)
     }
 
-    async fn get_price_from_cache(
+    /// Cross-checks a fresh USD price against the mint's configured Pyth
+    /// oracle. Prices further off than `oracle_max_deviation_pct` are logged
+    /// and counted, and fail with `OracleDeviation` when
+    /// `reject_oracle_deviation` is set. Mints without an oracle, and
+    /// oracles that are unreadable or older than `oracle_max_age_secs`, pass.
+    async fn check_oracle(&self, mint: &str, price: f64) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(oracle) = self.prices.oracles.get(mint) else {
+            return Ok(());
+        };
+        let oracle_price = match self
+            .rpc_client
+            .get_account(&Pubkey::from_str(oracle)?)
+            .await
+        {
+            Ok(account) => OraclePrice::from_pyth_account(&account),
+            Err(e) => {
+                eprintln!("Oracle {} for {} unreadable: {}", oracle, mint, e);
+                None
+            }
+        };
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs() as i64;
+        let Some(oracle_price) = oracle_price.filter(|oracle_price| {
+            oracle_price.price > 0.0
+                && now - oracle_price.publish_time <= self.prices.oracle_max_age_secs as i64
+        }) else {
+            eprintln!(
+                "Oracle {} for {} has no usable price, skipping check",
+                oracle, mint
+            );
+            return Ok(());
+        };
+
+        if oracle_price.deviation_pct(price) <= self.prices.oracle_max_deviation_pct {
+            return Ok(());
+        }
+        Metrics::increment(&self.metrics.oracle_deviations);
+        let error = TokenServiceError::OracleDeviation {
+            mint: mint.to_string(),
+            price,
+            oracle_price: oracle_price.price,
+        };
+        if self.prices.reject_oracle_deviation {
+            return Err(error.into());
+        }
+        eprintln!("{}", error);
+        Ok(())
+    }
+
+    /// Re-fetches the USD price of every cached token (every cached price
+    /// when metadata is disabled) in batches, within the price API
+    /// concurrency limit, and rewrites `token_prices` in one statement.
+    /// Prices go through the same oracle check as `get_price` first.
+    pub async fn refresh_all_prices(&self) -> Result<RefreshReport, Box<dyn std::error::Error>> {
+        self.in_context("refresh_all_prices", "", async {
+            let query = if self.metadata_enabled {
+                "SELECT mint FROM token_metadata ORDER BY mint"
+            } else {
+                "SELECT DISTINCT mint FROM token_prices ORDER BY mint"
+            };
+            let mints: Vec<String> = self
+                .db_client
+                .query(query, &[])
+                .await?
+                .iter()
+                .map(|row| row.get(0))
+                .collect();
+            let mints: Vec<&str> = mints.iter().map(String::as_str).collect();
+
+            let batches = mints
+                .chunks(coalesce::PRICE_IDS_LIMIT)
+                .map(|chunk| async move {
+                    let entries = coalesce::fetch_price_chunk(
+                        &self.http_client,
+                        &self.price_permits,
+                        JUPITER_PRICE_URL,
+                        chunk,
+                    )
+                    .await;
+                    (chunk, entries)
+                });
+
+            let mut report = RefreshReport::default();
+            let mut refreshed_mints = Vec::new();
+            let mut prices = Vec::new();
+            for (chunk, entries) in futures_util::future::join_all(batches).await {
+                let entries = match entries {
+                    Ok(entries) => entries,
+                    Err(e) => {
+                        eprintln!("Price batch of {} mints failed: {}", chunk.len(), e);
+                        report.failed += chunk.len();
+                        continue;
+                    }
+                };
+                for &mint in chunk {
+                    match entries
+                        .get(mint)
+                        .and_then(|entry| parse_price(mint, &entry["price"]).ok())
+                    {
+                        Some(price) => match self.check_oracle(mint, price).await {
+                            Ok(()) => {
+                                refreshed_mints.push(mint.to_string());
+                                prices.push(price);
+                            }
+                            Err(e) => {
+                                eprintln!("Not refreshing {}: {}", mint, e);
+                                report.failed += 1;
+                            }
+                        },
+                        None => report.failed += 1,
+                    }
+                }
+            }
+
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)?
+                .as_secs() as i64;
+            self.db_client
+                .execute(
+                    "INSERT INTO token_prices (mint, quote, price, last_updated) 
+                     SELECT mint, $3, price, $4 FROM UNNEST($1::TEXT[], $2::DOUBLE PRECISION[]) 
+                     AS refreshed (mint, price) 
+                     ON CONFLICT (mint, quote) DO UPDATE SET price = EXCLUDED.price, 
+                     last_updated = EXCLUDED.last_updated",
+                    &[&refreshed_mints, &prices, &USD, &now],
+                )
+                .await?;
+            for (mint, price) in refreshed_mints.iter().zip(&prices) {
+                self.record_price_history(mint, *price).await?;
+            }
+
+            report.refreshed = refreshed_mints.len();
+            Ok(report)
+        })
+        .await
+    }
+
+    /// `fetch_mint_price`, but a price the API recently reported as null or
+    /// absent is failed from the negative cache without asking again.
+    async fn fetch_usd_price_negative_cached(
         &self,
         mint: &str,
-    ) -> Result<Option<f64>, Box<dyn std::error::Error>> {
+    ) -> Result<f64, Box<dyn std::error::Error>> {
+        if !self.always_fresh_mints.contains(mint) {
+            match self.get_negative(mint, NEGATIVE_PRICE).await?.as_deref() {
+                Some("unavailable") => {
+                    return Err(TokenServiceError::PriceUnavailable {
+                        mint: mint.to_string(),
+                    }
+                    .into())
+                }
+                Some(_) => {
+                    return Err(TokenServiceError::PriceMissing {
+                        mint: mint.to_string(),
+                    }
+                    .into())
+                }
+                None => {}
+            }
+        }
+
+        let error = match self.fetch_mint_price(mint).await {
+            Ok(price) => {
+                self.clear_negative(mint, NEGATIVE_PRICE).await?;
+                return Ok(price);
+            }
+            Err(e) => e,
+        };
+        let reason = match TokenServiceError::cause(error.as_ref()) {
+            Some(TokenServiceError::PriceUnavailable { .. }) => Some("unavailable"),
+            Some(TokenServiceError::PriceMissing { .. }) => Some("missing"),
+            _ => None,
+        };
+        if let Some(reason) = reason {
+            self.save_negative(mint, NEGATIVE_PRICE, reason).await?;
+        }
+        Err(error)
+    }
+
+    /// Reason of a negative cache entry for `mint` and `kind` still within
+    /// the negative TTL.
+    async fn get_negative(
+        &self,
+        mint: &str,
+        kind: &str,
+    ) -> Result<Option<String>, Box<dyn std::error::Error>> {
+        if self.negative_cache_duration == 0 {
+            return Ok(None);
+        }
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs() as i64;
         let row = self
             .db_client
             .query_opt(
-                "SELECT price, last_updated FROM token_prices 
-                 WHERE mint = $1 AND last_updated > $2",
-                &[
-                    &mint,
-                    &(std::time::SystemTime::now()
-                        .duration_since(std::time::UNIX_EPOCH)?
-                        .as_secs() as i64
-                        - self.price_cache_duration as i64),
-                ],
+                "SELECT reason FROM token_negative_cache 
+                 WHERE mint = $1 AND kind = $2 AND last_updated > $3",
+                &[&mint, &kind, &(now - self.negative_cache_duration as i64)],
             )
             .await?;
-
         Ok(row.map(|row| row.get(0)))
     }
 
-    async fn save_price_to_cache(
+    async fn save_negative(
         &self,
         mint: &str,
-        price: f64,
+        kind: &str,
+        reason: &str,
     ) -> Result<(), Box<dyn std::error::Error>> {
+        if self.negative_cache_duration == 0 {
+            return Ok(());
+        }
         let now = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)?
             .as_secs() as i64;
+        self.db_client
+            .execute(
+                "INSERT INTO token_negative_cache (mint, kind, reason, last_updated) 
+                 VALUES ($1, $2, $3, $4)
+                 ON CONFLICT (mint, kind) DO UPDATE SET reason = $3, last_updated = $4",
+                &[&mint, &kind, &reason, &now],
+            )
+            .await?;
+        Ok(())
+    }
 
+    async fn clear_negative(
+        &self,
+        mint: &str,
+        kind: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if self.negative_cache_duration == 0 {
+            return Ok(());
+        }
         self.db_client
             .execute(
-                "INSERT INTO token_prices (mint, price, last_updated) 
-                 VALUES ($1, $2, $3)
-                 ON CONFLICT (mint) DO UPDATE SET price = $2, last_updated = $3",
-                &[&mint, &price, &now],
+                "DELETE FROM token_negative_cache WHERE mint = $1 AND kind = $2",
+                &[&mint, &kind],
             )
             .await?;
         Ok(())
     }
+
+    /// USD prices for `mints`, in the caller's order; `None` where the price
+    /// API has no price, or under `BatchMode::BestEffort` where its batch
+    /// request failed or the oracle check rejected the price. Duplicates (after canonicalisation) are looked up
+    /// once, and cache misses are fetched in as few requests as possible.
+    pub async fn get_prices(
+        &self,
+        mints: &[&str],
+        mode: BatchMode,
+    ) -> Result<Vec<Option<f64>>, Box<dyn std::error::Error>> {
+        self.in_context("get_prices", "", async {
+            let mints = mints
+                .iter()
+                .map(|mint| canonical_mint(mint))
+                .collect::<Result<Vec<_>, _>>()?;
+            let mut unique: Vec<&str> = mints.iter().map(String::as_str).collect();
+            unique.sort_unstable();
+            unique.dedup();
+
+            let mut prices: HashMap<&str, Option<f64>> = HashMap::new();
+            let mut missing = Vec::new();
+            for mint in unique {
+                if self.always_fresh_mints.contains(mint) {
+                    missing.push(mint);
+                    continue;
+                }
+                match self.get_price_from_cache(mint, USD).await? {
+                    Some(price) => {
+                        prices.insert(mint, Some(price));
+                    }
+                    None => missing.push(mint),
+                }
+            }
+
+            for chunk in missing.chunks(coalesce::PRICE_IDS_LIMIT) {
+                let entries = coalesce::fetch_price_chunk(
+                    &self.http_client,
+                    &self.price_permits,
+                    JUPITER_PRICE_URL,
+                    chunk,
+                )
+                .await;
+                let entries = match entries {
+                    Ok(entries) => entries,
+                    Err(e) if mode == BatchMode::BestEffort => {
+                        eprintln!("Price batch of {} mints failed: {}", chunk.len(), e);
+                        continue;
+                    }
+                    Err(e) => return Err(e as Box<dyn std::error::Error>),
+                };
+                for &mint in chunk {
+                    let mut price = entries
+                        .get(mint)
+                        .and_then(|entry| parse_price(mint, &entry["price"]).ok());
+                    if let Some(fresh) = price {
+                        match self.check_oracle(mint, fresh).await {
+                            Ok(()) => {
+                                self.record_price_history(mint, fresh).await?;
+                                self.save_price_to_cache(mint, USD, fresh).await?;
+                            }
+                            Err(e) if mode == BatchMode::BestEffort => {
+                                eprintln!("Dropping price of {}: {}", mint, e);
+                                price = None;
+                            }
+                            Err(e) => return Err(e),
+                        }
+                    }
+                    prices.insert(mint, price);
+                }
+            }
+
+            Ok(mints
+                .iter()
+                .map(|mint| prices.get(mint.as_str()).copied().flatten())
+                .collect())
+        })
+        .await
+    }
+
+    /// Whether the mint's USD price, as `get_price` serves it (cached within
+    /// its TTL), is strictly below `threshold`, along with the price observed.
+    pub async fn price_below(
+        &self,
+        mint: &str,
+        threshold: f64,
+    ) -> Result<(bool, f64), Box<dyn std::error::Error>> {
+        self.in_context("price_below", mint, async {
+            let price = self.get_price(mint).await?;
+            Ok((compare_price(price, threshold).is_lt(), price))
+        })
+        .await
+    }
+
+    /// Whether the mint's USD price is strictly above `threshold`, along with
+    /// the price observed.
+    pub async fn price_above(
+        &self,
+        mint: &str,
+        threshold: f64,
+    ) -> Result<(bool, f64), Box<dyn std::error::Error>> {
+        self.in_context("price_above", mint, async {
+            let price = self.get_price(mint).await?;
+            Ok((compare_price(price, threshold).is_gt(), price))
+        })
+        .await
+    }
+
+    /// Prices the mint in USD and every configured quote currency.
+    pub async fn get_prices_in_all_quotes(
+        &self,
+        mint: &str,
+    ) -> Result<HashMap<String, f64>, Box<dyn std::error::Error>> {
+        self.in_context("get_prices_in_all_quotes", mint, async {
+            let mut prices = HashMap::new();
+            prices.insert(USD.to_string(), self.get_price_in(mint, USD).await?);
+            for quote in self.prices.quote_currencies.keys() {
+                prices.insert(quote.clone(), self.get_price_in(mint, quote).await?);
+            }
+            Ok(prices)
+        })
+        .await
+    }
+
+    async fn get_price_from_cache(
+        &self,
+        mint: &str,
+        quote: &str,
+    ) -> Result<Option<f64>, Box<dyn std::error::Error>> {
+        let ttl = self.price_ttl(mint).await?;
+        let row = self
+            .db_client
+            .query_opt(
+                "SELECT price, last_updated FROM token_prices 
+                 WHERE mint = $1 AND quote = $2 AND last_updated > $3",
+                &[
+                    &mint,
+                    &quote,
+                    &(std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)?
+                        .as_secs() as i64
+                        - ttl as i64),
+                ],
+            )
+            .await?;
+
+        Ok(row.map(|row| row.get(0)))
+    }
+
+    /// Price cache TTL for the mint: the fixed duration, or one adapted to
+    /// the volatility of its recent history when `adaptive_ttl` is enabled.
+    async fn price_ttl(&self, mint: &str) -> Result<u64, Box<dyn std::error::Error>> {
+        if !self.prices.adaptive_ttl {
+            return Ok(self.price_cache_duration);
+        }
+
+        let rows = self
+            .db_client
+            .query(
+                "SELECT price FROM token_price_history 
+                 WHERE mint = $1 ORDER BY observed_at DESC LIMIT $2",
+                &[&mint, &self.prices.volatility_samples],
+            )
+            .await?;
+        let mut prices: Vec<f64> = rows.iter().map(|row| row.get(0)).collect();
+        prices.reverse();
+
+        Ok(stats::adaptive_ttl(
+            &prices,
+            self.price_cache_duration,
+            &self.prices,
+        ))
+    }
+
+    /// Time-weighted average USD price over the trailing `window`, from the
+    /// recorded price history. Each price counts for as long as it was the
+    /// latest observation, so bursts of samples do not dominate.
+    pub async fn get_twap(
+        &self,
+        mint: &str,
+        window: Duration,
+    ) -> Result<f64, Box<dyn std::error::Error>> {
+        self.in_context("get_twap", mint, async {
+            let mint = &canonical_mint(mint)?;
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)?
+                .as_secs() as i64;
+            let start = now - window.as_secs() as i64;
+
+            let points = self.price_history_since(mint, start).await?;
+
+            stats::time_weighted_average(&points, start, now)
+                .ok_or_else(|| format!("No price history for {}", mint).into())
+        })
+        .await
+    }
+
+    /// Pearson correlation of the two mints' USD price returns over the
+    /// trailing `window`. Each of `mint_a`'s observations is paired with the
+    /// latest `mint_b` observation at or before it.
+    pub async fn correlation(
+        &self,
+        mint_a: &str,
+        mint_b: &str,
+        window: Duration,
+    ) -> Result<f64, Box<dyn std::error::Error>> {
+        self.in_context("correlation", mint_a, async {
+            let mint_a = &canonical_mint(mint_a)?;
+            let mint_b = &canonical_mint(mint_b)?;
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)?
+                .as_secs() as i64;
+            let start = now - window.as_secs() as i64;
+
+            let history_a: Vec<(i64, f64)> = self
+                .price_history_since(mint_a, start)
+                .await?
+                .into_iter()
+                .filter(|(observed_at, _)| *observed_at >= start)
+                .collect();
+            let history_b = self.price_history_since(mint_b, start).await?;
+            let aligned = stats::align_as_of(&history_a, &history_b);
+
+            stats::return_correlation(&aligned, MIN_CORRELATION_SAMPLES).ok_or_else(|| {
+                format!(
+                    "Cannot correlate {} and {}: {} aligned points (need {}) or a flat series",
+                    mint_a,
+                    mint_b,
+                    aligned.len(),
+                    MIN_CORRELATION_SAMPLES + 1
+                )
+                .into()
+            })
+        })
+        .await
+    }
+
+    /// Recorded USD prices from `start` on, oldest first, preceded by the
+    /// last observation before `start` (the price in effect at `start`).
+    async fn price_history_since(
+        &self,
+        mint: &str,
+        start: i64,
+    ) -> Result<Vec<(i64, f64)>, Box<dyn std::error::Error>> {
+        let rows = self
+            .db_client
+            .query(
+                "(SELECT observed_at, price FROM token_price_history 
+                  WHERE mint = $1 AND observed_at < $2 ORDER BY observed_at DESC LIMIT 1) 
+                 UNION ALL 
+                 (SELECT observed_at, price FROM token_price_history 
+                  WHERE mint = $1 AND observed_at >= $2) 
+                 ORDER BY observed_at",
+                &[&mint, &start],
+            )
+            .await?;
+        Ok(rows.iter().map(|row| (row.get(0), row.get(1))).collect())
+    }
+
+    async fn record_price_history(
+        &self,
+        mint: &str,
+        price: f64,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs() as i64;
+
+        for sink in &self.price_sinks {
+            if let Err(e) = sink.record(mint, price, now).await {
+                eprintln!("Price sink failed to record {}: {}", mint, e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds a sink alongside the Postgres history; every fresh USD price is
+    /// sent to each sink in the order they were added.
+    pub fn add_price_sink(&mut self, sink: Arc<dyn PriceSink>) {
+        self.price_sinks.push(sink);
+    }
+
+    async fn save_price_to_cache(
+        &self,
+        mint: &str,
+        quote: &str,
+        price: f64,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs() as i64;
+
+        self.db_client
+            .execute(
+                "INSERT INTO token_prices (mint, quote, price, last_updated) 
+                 VALUES ($1, $2, $3, $4)
+                 ON CONFLICT (mint, quote) DO UPDATE SET price = $3, last_updated = $4",
+                &[&mint, &quote, &price, &now],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Estimates the slippage, in percent, of buying `usd_amount` worth of the
+    /// mint with USDC, by comparing the quote's execution price with the spot
+    /// price. Results are cached briefly per power-of-two size bucket.
+    pub async fn estimate_slippage(
+        &self,
+        mint: &str,
+        usd_amount: f64,
+    ) -> Result<f64, Box<dyn std::error::Error>> {
+        self.in_context("estimate_slippage", mint, async {
+            if !usd_amount.is_finite() || usd_amount <= 0.0 {
+                return Err(format!("Invalid order size: {}", usd_amount).into());
+            }
+            let mint = &canonical_mint(mint)?;
+
+            let size_bucket = usd_amount.log2().floor() as i32;
+            if let Some(slippage) = self.get_slippage_from_cache(mint, size_bucket).await? {
+                return Ok(slippage);
+            }
+
+            let spot = self.get_price(mint).await?;
+            let decimals = self.get_decimals(mint).await?;
+            let out_amount = self
+                .fetch_quote_out_amount(USDC_MINT, mint, usd_amount)
+                .await?;
+            if out_amount == 0 {
+                return Err(TokenServiceError::NoRoute {
+                    mint: mint.to_string(),
+                }
+                .into());
+            }
+
+            let tokens_out = out_amount as f64 / 10f64.powi(decimals as i32);
+            let execution_price = usd_amount / tokens_out;
+            let slippage = (execution_price - spot) / spot * 100.0;
+
+            self.save_slippage_to_cache(mint, size_bucket, slippage)
+                .await?;
+            Ok(slippage)
+        })
+        .await
+    }
+
+    /// Returns the raw `outAmount` of a Jupiter quote for swapping `usd_amount`
+    /// of `input_mint` (assumed USDC-denominated) into `output_mint`.
+    async fn fetch_quote_out_amount(
+        &self,
+        input_mint: &str,
+        output_mint: &str,
+        usd_amount: f64,
+    ) -> Result<u64, Box<dyn std::error::Error>> {
+        let amount = (usd_amount * 10f64.powi(USDC_DECIMALS)).round() as u64;
+        self.fetch_quote_raw(input_mint, output_mint, amount).await
+    }
+
+    /// Returns the raw `outAmount` of a Jupiter quote for swapping the raw
+    /// `amount` of `input_mint` into `output_mint`.
+    async fn fetch_quote_raw(
+        &self,
+        input_mint: &str,
+        output_mint: &str,
+        amount: u64,
+    ) -> Result<u64, Box<dyn std::error::Error>> {
+        let url = format!(
+            "https://quote-api.jup.ag/v6/quote?inputMint={}&outputMint={}&amount={}",
+            input_mint, output_mint, amount
+        );
+        let _permit = self.price_permits.acquire().await?;
+        let response = self.http_client.get(&url).send().await?;
+        let status = response.status();
+        let data: Value = response.json().await?;
+
+        if !status.is_success() || data.get("error").is_some() {
+            if data["errorCode"] == "COULD_NOT_FIND_ANY_ROUTE"
+                || data["errorCode"] == "TOKEN_NOT_TRADABLE"
+            {
+                // Name the token being traded, not the USDC side.
+                let mint = if output_mint == USDC_MINT {
+                    input_mint
+                } else {
+                    output_mint
+                };
+                return Err(TokenServiceError::NoRoute {
+                    mint: mint.to_string(),
+                }
+                .into());
+            }
+            return Err(format!("Quote request failed ({}): {}", status, data).into());
+        }
+
+        let out_amount = data["outAmount"]
+            .as_str()
+            .ok_or_else(|| format!("Quote response missing outAmount: {}", data))?;
+        Ok(u64::from_str(out_amount)?)
+    }
+
+    async fn get_slippage_from_cache(
+        &self,
+        mint: &str,
+        size_bucket: i32,
+    ) -> Result<Option<f64>, Box<dyn std::error::Error>> {
+        let row = self
+            .db_client
+            .query_opt(
+                "SELECT slippage FROM token_slippage 
+                 WHERE mint = $1 AND size_bucket = $2 AND last_updated > $3",
+                &[
+                    &mint,
+                    &size_bucket,
+                    &(std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)?
+                        .as_secs() as i64
+                        - self.slippage_cache_duration as i64),
+                ],
+            )
+            .await?;
+
+        Ok(row.map(|row| row.get(0)))
+    }
+
+    async fn save_slippage_to_cache(
+        &self,
+        mint: &str,
+        size_bucket: i32,
+        slippage: f64,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs() as i64;
+
+        self.db_client
+            .execute(
+                "INSERT INTO token_slippage (mint, size_bucket, slippage, last_updated) 
+                 VALUES ($1, $2, $3, $4)
+                 ON CONFLICT (mint, size_bucket) DO UPDATE SET slippage = $3, last_updated = $4",
+                &[&mint, &size_bucket, &slippage, &now],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Counts cached metadata and price rows by how long ago they were
+    /// written, one entry per bucket in `AgeBucket::ALL` order.
+    pub async fn cache_age_histogram(
+        &self,
+    ) -> Result<Vec<(AgeBucket, usize)>, Box<dyn std::error::Error>> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs() as i64;
+        let cached_rows = if self.metadata_enabled {
+            "SELECT last_updated FROM token_metadata 
+             UNION ALL SELECT last_updated FROM token_prices"
+        } else {
+            "SELECT last_updated FROM token_prices"
+        };
+
+        let rows = self
+            .db_client
+            .query(
+                &format!(
+                    "SELECT CASE 
+                         WHEN $1 - last_updated < 60 THEN 0 
+                         WHEN $1 - last_updated < 600 THEN 1 
+                         WHEN $1 - last_updated < 3600 THEN 2 
+                         ELSE 3 END AS bucket, COUNT(*) 
+                     FROM ({}) AS cached GROUP BY bucket",
+                    cached_rows
+                ),
+                &[&now],
+            )
+            .await?;
+
+        let mut counts = [0usize; 4];
+        for row in rows {
+            let bucket: i32 = row.get(0);
+            let count: i64 = row.get(1);
+            counts[bucket as usize] = count as usize;
+        }
+        Ok(AgeBucket::ALL.into_iter().zip(counts).collect())
+    }
+
+    /// Distinct quote currencies with at least one cached price, sorted.
+    pub async fn cached_quote_currencies(&self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        self.in_context("cached_quote_currencies", "", async {
+            let rows = self
+                .db_client
+                .query(
+                    "SELECT DISTINCT quote FROM token_prices ORDER BY quote",
+                    &[],
+                )
+                .await?;
+            Ok(rows.iter().map(|row| row.get(0)).collect())
+        })
+        .await
+    }
+
+    pub async fn get_epoch_info(&self) -> Result<EpochInfo, Box<dyn std::error::Error>> {
+        self.in_context("get_epoch_info", "", async {
+            Ok(self.rpc_client.get_epoch_info().await?)
+        })
+        .await
+    }
+
+    pub async fn get_inflation_rate(&self) -> Result<RpcInflationRate, Box<dyn std::error::Error>> {
+        self.in_context("get_inflation_rate", "", async {
+            Ok(self.rpc_client.get_inflation_rate().await?)
+        })
+        .await
+    }
+
+    /// Current counters and gauges in the Prometheus text format, for callers
+    /// to expose over whatever transport they already have.
+    pub fn metrics_snapshot(&self) -> String {
+        let mut out = String::new();
+        self.metrics.render(&mut out);
+
+        let background_tasks = self
+            .background_tasks
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|task| !task.is_finished())
+            .count();
+        metrics::write_metric(
+            &mut out,
+            "ready",
+            "gauge",
+            "1 once startup checks have passed.",
+            &[("", self.is_ready() as u8 as f64)],
+        );
+        metrics::write_metric(
+            &mut out,
+            "background_tasks",
+            "gauge",
+            "Background subscriptions and watchers still running.",
+            &[("", background_tasks as f64)],
+        );
+
+        let endpoints = self.websocket_health();
+        let labels: Vec<String> = endpoints
+            .iter()
+            .map(|endpoint| format!("endpoint=\"{}\"", endpoint.url))
+            .collect();
+        let active: Vec<(&str, f64)> = endpoints
+            .iter()
+            .zip(&labels)
+            .map(|(endpoint, labels)| (labels.as_str(), endpoint.active as u8 as f64))
+            .collect();
+        let failures: Vec<(&str, f64)> = endpoints
+            .iter()
+            .zip(&labels)
+            .map(|(endpoint, labels)| (labels.as_str(), endpoint.total_failures as f64))
+            .collect();
+        metrics::write_metric(
+            &mut out,
+            "websocket_endpoint_active",
+            "gauge",
+            "1 for the WebSocket endpoint subscriptions currently use.",
+            &active,
+        );
+        metrics::write_metric(
+            &mut out,
+            "websocket_endpoint_failures_total",
+            "counter",
+            "Connection failures seen per WebSocket endpoint.",
+            &failures,
+        );
+
+        if let Some(budget) = self.rpc_budget() {
+            metrics::write_metric(
+                &mut out,
+                "rpc_rate_limit_remaining",
+                "gauge",
+                "Requests left in the RPC provider's rate-limit window.",
+                &[("", budget.remaining as f64)],
+            );
+        }
+        out
+    }
+
+    /// Metadata and price cache hit ratio over the last `window`, unlike the
+    /// cumulative counters in `metrics_snapshot`.
+    pub fn hit_ratio(&self, window: Duration) -> HitRatio {
+        self.metrics
+            .lookups
+            .ratio(window, std::time::Instant::now())
+    }
+
+    pub fn websocket_health(&self) -> Vec<EndpointHealth> {
+        self.websocket_endpoints.health()
+    }
+
+    /// The RPC provider's last reported rate-limit budget, `None` when its
+    /// responses carry no rate-limit headers.
+    pub fn rpc_budget(&self) -> Option<RateLimitBudget> {
+        self.rpc_limits.rate_limiter.budget()
+    }
+
+    /// Fetches `pubkeys` with `getMultipleAccounts`, in batches of at most
+    /// `MULTIPLE_ACCOUNTS_LIMIT`, and decodes each account with `decode`.
+    /// Returns one result per key, in order: `AccountNotFound` for missing
+    /// accounts, and `BatchFailed` carrying the RPC error for every key of a
+    /// batch that failed.
+    pub async fn get_multiple_decoded<T>(
+        &self,
+        pubkeys: &[Pubkey],
+        decode: impl Fn(&Account) -> Result<T, Box<dyn std::error::Error>>,
+    ) -> Vec<Result<T, Box<dyn std::error::Error>>> {
+        let mut results = Vec::with_capacity(pubkeys.len());
+        for chunk in pubkeys.chunks(MULTIPLE_ACCOUNTS_LIMIT) {
+            let accounts = match self.rpc_client.get_multiple_accounts(chunk).await {
+                Ok(accounts) => accounts,
+                Err(e) => {
+                    let source = Arc::new(e);
+                    results.extend(chunk.iter().map(|_| {
+                        Err(TokenServiceError::BatchFailed {
+                            source: source.clone(),
+                        }
+                        .into())
+                    }));
+                    continue;
+                }
+            };
+            results.extend(chunk.iter().zip(accounts).map(|(pubkey, account)| {
+                match account {
+                    Some(account) => decode(&account),
+                    None => Err(TokenServiceError::AccountNotFound {
+                        account: pubkey.to_string(),
+                    }
+                    .into()),
+                }
+            }));
+        }
+        results
+    }
+
+    /// Subscribes to logs of transactions mentioning `program_id`. The
+    /// subscription reconnects on its own and backfills anything missed while
+    /// disconnected; it stops once the returned receiver is dropped. With
+    /// `readiness.gate_background_tasks` set it connects only after
+    /// `check_readiness` passes.
+    pub fn subscribe_program_logs(
+        &self,
+        program_id: &str,
+    ) -> Result<mpsc::Receiver<LogEvent>, Box<dyn std::error::Error>> {
+        let program = Pubkey::from_str(program_id)?;
+        let (sender, receiver) = mpsc::channel(1024);
+        self.spawn_background(listener::run_log_subscription(
+            self.websocket_endpoints.clone(),
+            http::nonblocking_rpc_client(
+                &self.rpc_url,
+                self.http_proxy.as_deref(),
+                &self.rpc_limits,
+            )?,
+            program,
+            sender,
+        ));
+        Ok(receiver)
+    }
+
+    /// Watches the Jupiter DCA orders owned by the `user` wallet, emitting an
+    /// event whenever one is opened, filled or closed. Orders that already
+    /// exist when the watch starts are not reported as opened. Gated on
+    /// readiness like `subscribe_program_logs`.
+    pub fn watch_user_dca(
+        &self,
+        user: &str,
+    ) -> Result<mpsc::Receiver<DcaOrderEvent>, Box<dyn std::error::Error>> {
+        let user = Pubkey::from_str(user.trim())?;
+        let (sender, receiver) = mpsc::channel(1024);
+        self.spawn_background(dca::run_user_dca_subscription(
+            self.websocket_endpoints.clone(),
+            http::nonblocking_rpc_client(
+                &self.rpc_url,
+                self.http_proxy.as_deref(),
+                &self.rpc_limits,
+            )?,
+            user,
+            sender,
+        ));
+        Ok(receiver)
+    }
+
+    /// Watches the members of the verified collection `collection_mint`
+    /// through DAS, emitting additions, removals and metadata changes found
+    /// on each pass, one pass every `interval`. Needs an RPC provider that
+    /// serves DAS. Gated on readiness like `subscribe_program_logs`.
+    pub fn watch_collection(
+        &self,
+        collection_mint: &str,
+        interval: Duration,
+    ) -> Result<mpsc::Receiver<CollectionUpdate>, Box<dyn std::error::Error>> {
+        let collection = canonical_mint(collection_mint)?;
+        let (sender, receiver) = mpsc::channel(1024);
+        self.spawn_background(das::run_collection_watch(
+            http::nonblocking_rpc_client(
+                &self.rpc_url,
+                self.http_proxy.as_deref(),
+                &self.rpc_limits,
+            )?,
+            collection,
+            interval,
+            sender,
+        ));
+        Ok(receiver)
+    }
+
+    /// Streams every Jupiter DCA program transaction, recording each in
+    /// `dca_events` before emitting it. Signatures already recorded, e.g.
+    /// replayed by a reconnect backfill or seen before a restart, are not
+    /// emitted again. Gated on readiness like `subscribe_program_logs`.
+    pub fn subscribe_dca_events(
+        &self,
+    ) -> Result<mpsc::Receiver<DcaEvent>, Box<dyn std::error::Error>> {
+        let mut logs = self.subscribe_program_logs(&programs::JUPITER_DCA.to_string())?;
+        let (sender, receiver) = mpsc::channel(1024);
+        let db_client = self.db_client.clone();
+        self.spawn_background(async move {
+            while let Some(log) = logs.recv().await {
+                let event = DcaEvent::from(log);
+                match dca::record_event(&db_client, &event).await {
+                    Ok(false) => continue,
+                    Ok(true) => {}
+                    Err(e) => eprintln!("Failed to record DCA event {}: {}", event.signature, e),
+                }
+                if sender.send(event).await.is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(receiver)
+    }
+
+    /// Whether the transaction at `signature` succeeded and emitted a DCA
+    /// fill matching `expected`. A transaction without a fill never matches.
+    pub async fn verify_fill(
+        &self,
+        signature: &str,
+        expected: &DcaFillExpectation,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        self.in_context("verify_fill", &expected.output_mint, async {
+            let transaction = self
+                .rpc_client
+                .get_transaction_with_config(
+                    &Signature::from_str(signature.trim())?,
+                    RpcTransactionConfig {
+                        encoding: Some(UiTransactionEncoding::Json),
+                        commitment: None,
+                        max_supported_transaction_version: Some(0),
+                    },
+                )
+                .await?;
+            let Some(meta) = transaction.transaction.meta else {
+                return Ok(false);
+            };
+            if meta.err.is_some() {
+                return Ok(false);
+            }
+            let OptionSerializer::Some(logs) = meta.log_messages else {
+                return Ok(false);
+            };
+
+            Ok(DcaFill::from_logs(&logs).is_some_and(|fill| expected.matches(&fill)))
+        })
+        .await
+    }
+
+    /// DCA events recorded at or after `since` (a slot), oldest first.
+    pub async fn replay_events(
+        &self,
+        since: i64,
+    ) -> Result<Vec<DcaEvent>, Box<dyn std::error::Error>> {
+        self.in_context("replay_events", "", async {
+            Ok(dca::replay_events(&self.db_client, since).await?)
+        })
+        .await
+    }
+
+    /// Keeps the SOL/USD rate current in-process, re-fetching it every
+    /// `interval`; a failed fetch keeps the last rate. The first call starts
+    /// the poller, seeded from `get_price`, and later calls share it. While
+    /// it runs, USD conversions of SOL amounts read the rate from here. With
+    /// `readiness.gate_background_tasks` set the rate stays at its seed
+    /// until `check_readiness` passes.
+    pub async fn watch_sol_usd(
+        &self,
+        interval: Duration,
+    ) -> Result<watch::Receiver<f64>, Box<dyn std::error::Error>> {
+        if let Some(receiver) = self.sol_usd.lock().unwrap().as_ref() {
+            return Ok(receiver.clone());
+        }
+
+        let sol = spl_token::native_mint::id().to_string();
+        let (sender, receiver) = watch::channel(self.get_price(&sol).await?);
+        {
+            let mut sol_usd = self.sol_usd.lock().unwrap();
+            if let Some(existing) = sol_usd.as_ref() {
+                return Ok(existing.clone());
+            }
+            *sol_usd = Some(receiver.clone());
+        }
+
+        let http_client = self.http_client.clone();
+        let permits = self.price_permits.clone();
+        self.spawn_background(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                let price = coalesce::fetch_price_chunk(
+                    &http_client,
+                    &permits,
+                    JUPITER_PRICE_URL,
+                    &[sol.as_str()],
+                )
+                .await
+                .map_err(|e| e as Box<dyn std::error::Error>)
+                .and_then(|entries| {
+                    parse_price(
+                        &sol,
+                        entries.get(&sol).map_or(&Value::Null, |e| &e["price"]),
+                    )
+                });
+                match price {
+                    Ok(price) => {
+                        sender.send_if_modified(|current| {
+                            let changed = *current != price;
+                            *current = price;
+                            changed
+                        });
+                    }
+                    Err(e) => eprintln!("SOL/USD refresh failed, keeping last rate: {}", e),
+                }
+            }
+        });
+        Ok(receiver)
+    }
+
+    /// SOL/USD from the `watch_sol_usd` poller when it runs, `get_price`
+    /// otherwise.
+    async fn sol_usd_price(&self) -> Result<f64, Box<dyn std::error::Error>> {
+        if let Some(receiver) = self.sol_usd.lock().unwrap().as_ref() {
+            return Ok(*receiver.borrow());
+        }
+        self.get_price(&spl_token::native_mint::id().to_string())
+            .await
+    }
+
+    /// Verifies RPC, Postgres and price API connectivity, warms the cache
+    /// with the configured `warmup_mints` and, after `warmup_delay_secs`,
+    /// marks the service ready. Until then `is_ready` is false and, with
+    /// `readiness.gate_background_tasks` set, background tasks
+    /// (subscriptions, pollers) hold off. A failed check
+    /// leaves the service not ready; call again to retry.
+    pub async fn check_readiness(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.rpc_client
+            .get_slot()
+            .await
+            .map_err(|e| format!("RPC check failed: {}", e))?;
+        self.db_client
+            .execute("SELECT 1", &[])
+            .await
+            .map_err(|e| format!("Database check failed: {}", e))?;
+        self.fetch_mint_price(USDC_MINT)
+            .await
+            .map_err(|e| format!("Price API check failed: {}", e))?;
+
+        for mint in &self.readiness.warmup_mints {
+            if self.metadata_enabled {
+                if let Err(e) = self.get_metadata(mint).await {
+                    eprintln!("Warm-up metadata for {} failed: {}", mint, e);
+                }
+            }
+            if let Err(e) = self.get_price(mint).await {
+                eprintln!("Warm-up price for {} failed: {}", mint, e);
+            }
+        }
+        tokio::time::sleep(Duration::from_secs(self.readiness.warmup_delay_secs)).await;
+
+        self.ready.send_replace(true);
+        Ok(())
+    }
+
+    /// Whether `check_readiness` has passed.
+    pub fn is_ready(&self) -> bool {
+        *self.ready.borrow()
+    }
+
+    /// Spawns a task that is cancelled by `shutdown` and, with
+    /// `readiness.gate_background_tasks` set, starts once the service is
+    /// ready.
+    fn spawn_background(&self, task: impl Future<Output = ()> + Send + 'static) {
+        let mut shutdown = self.shutdown.subscribe();
+        let mut ready = self.ready.subscribe();
+        let gated = self.readiness.gate_background_tasks;
+        let handle = tokio::spawn(async move {
+            let task = async move {
+                if !gated || ready.wait_for(|ready| *ready).await.is_ok() {
+                    task.await;
+                }
+            };
+            tokio::select! {
+                _ = task => {}
+                _ = shutdown.wait_for(|stopping| *stopping) => {}
+            }
+        });
+
+        let mut tasks = self.background_tasks.lock().unwrap();
+        tasks.retain(|task| !task.is_finished());
+        tasks.push(handle);
+    }
+
+    /// Stops every background task (log subscriptions and other watchers)
+    /// and waits up to `timeout` for them to wind down, aborting any that do
+    /// not. Returns false if some task had to be aborted.
+    pub async fn shutdown(&self, timeout: Duration) -> bool {
+        self.shutdown.send_replace(true);
+        let tasks: Vec<JoinHandle<()>> = self.background_tasks.lock().unwrap().drain(..).collect();
+        let aborts: Vec<_> = tasks.iter().map(|task| task.abort_handle()).collect();
+
+        let joined = tokio::time::timeout(timeout, futures_util::future::join_all(tasks)).await;
+        if joined.is_err() {
+            eprintln!(
+                "Background tasks did not stop within {:?}, aborting",
+                timeout
+            );
+            for abort in aborts {
+                abort.abort();
+            }
+            return false;
+        }
+        true
+    }
+
+    /// Returns a newest-first timeline of token-program activity on the mint
+    /// across its `limit` most recent transactions, fetched
+    /// `ACTIVITY_FETCH_CONCURRENCY` at a time.
+    pub async fn recent_activity(
+        &self,
+        mint: &str,
+        limit: usize,
+    ) -> Result<Vec<MintActivity>, Box<dyn std::error::Error>> {
+        self.in_context("recent_activity", mint, async {
+            let mint = &canonical_mint(mint)?;
+            let mint_pubkey = Pubkey::from_str(mint)?;
+
+            let mut signatures = Vec::with_capacity(limit);
+            let mut before = None;
+            while signatures.len() < limit {
+                let page = self
+                    .rpc_client
+                    .get_signatures_for_address_with_config(
+                        &mint_pubkey,
+                        GetConfirmedSignaturesForAddress2Config {
+                            before,
+                            until: None,
+                            limit: Some((limit - signatures.len()).min(SIGNATURES_PAGE_LIMIT)),
+                            commitment: None,
+                        },
+                    )
+                    .await?;
+                let Some(oldest) = page.last() else {
+                    break;
+                };
+                before = Some(Signature::from_str(&oldest.signature)?);
+                signatures.extend(page.into_iter().map(|status| status.signature));
+            }
+
+            let parsed = signatures
+                .iter()
+                .map(|signature| Signature::from_str(signature))
+                .collect::<Result<Vec<_>, _>>()?;
+            let transactions: Vec<_> = futures_util::stream::iter(&parsed)
+                .map(|signature| {
+                    self.rpc_client.get_transaction_with_config(
+                        signature,
+                        RpcTransactionConfig {
+                            encoding: Some(UiTransactionEncoding::JsonParsed),
+                            commitment: None,
+                            max_supported_transaction_version: Some(0),
+                        },
+                    )
+                })
+                .buffered(ACTIVITY_FETCH_CONCURRENCY)
+                .collect()
+                .await;
+
+            let mut timeline = Vec::new();
+            for (signature, transaction) in signatures.iter().zip(transactions) {
+                timeline.extend(activity::classify_transaction(
+                    signature,
+                    mint,
+                    &transaction?,
+                ));
+            }
+
+            Ok(timeline)
+        })
+        .await
+    }
+
+    /// Time since the mint's oldest transaction. The scan gives up after
+    /// `AGE_SCAN_MAX_PAGES` pages of signatures, so for very busy mints the
+    /// result is a lower bound. Scans that reach the first transaction are
+    /// remembered in `token_first_seen`.
+    pub async fn get_token_age(&self, mint: &str) -> Result<Duration, Box<dyn std::error::Error>> {
+        self.in_context("get_token_age", mint, async {
+            Ok(self.token_age(mint).await?.0)
+        })
+        .await
+    }
+
+    /// `get_token_age` along with whether the scan reached the mint's first
+    /// transaction, i.e. whether the age is exact rather than a lower bound.
+    async fn token_age(&self, mint: &str) -> Result<(Duration, bool), Box<dyn std::error::Error>> {
+        let mint = canonical_mint(mint)?;
+        let cached = self
+            .get_first_seen(std::slice::from_ref(&mint))
+            .await?
+            .remove(&mint);
+        let (first_seen, complete) = match cached {
+            Some(first_seen) => (first_seen, true),
+            None => self.scan_first_seen(&mint).await?,
+        };
+        Ok((age_since(first_seen)?, complete))
+    }
+
+    /// `get_token_age` for many mints: ages in `token_first_seen` are read in
+    /// one query and only the rest are scanned, `AGE_SCAN_CONCURRENCY` at a
+    /// time. Mints whose scan fails or is cut short by `AGE_SCAN_MAX_PAGES`
+    /// are left out of the result.
+    pub async fn get_token_ages(
+        &self,
+        mints: &[&str],
+    ) -> Result<HashMap<String, Duration>, Box<dyn std::error::Error>> {
+        self.in_context("get_token_ages", "", async {
+            let mut mints = mints
+                .iter()
+                .map(|mint| canonical_mint(mint))
+                .collect::<Result<Vec<_>, _>>()?;
+            mints.sort();
+            mints.dedup();
+
+            let mut first_seen = self.get_first_seen(&mints).await?;
+            let uncached: Vec<&String> = mints
+                .iter()
+                .filter(|mint| !first_seen.contains_key(*mint))
+                .collect();
+            let scans: Vec<_> = futures_util::stream::iter(uncached)
+                .map(|mint| async move { (mint, self.scan_first_seen(mint).await) })
+                .buffer_unordered(AGE_SCAN_CONCURRENCY)
+                .collect()
+                .await;
+            for (mint, scan) in scans {
+                match scan {
+                    Ok((oldest, true)) => {
+                        first_seen.insert(mint.clone(), oldest);
+                    }
+                    Ok((_, false)) => eprintln!("Age scan of {} was cut short", mint),
+                    Err(e) => eprintln!("Could not determine age of {}: {}", mint, e),
+                }
+            }
+
+            first_seen
+                .into_iter()
+                .map(|(mint, first_seen)| Ok((mint, age_since(first_seen)?)))
+                .collect()
+        })
+        .await
+    }
+
+    async fn get_first_seen(
+        &self,
+        mints: &[String],
+    ) -> Result<HashMap<String, i64>, Box<dyn std::error::Error>> {
+        let rows = self
+            .db_client
+            .query(
+                "SELECT mint, first_seen FROM token_first_seen WHERE mint = ANY($1)",
+                &[&mints],
+            )
+            .await?;
+        Ok(rows.iter().map(|row| (row.get(0), row.get(1))).collect())
+    }
+
+    /// Block time of the mint's oldest transaction, walking signatures back
+    /// from the newest, and whether the walk got to the end within
+    /// `AGE_SCAN_MAX_PAGES`. Only complete walks are stored in
+    /// `token_first_seen`. Pages are read through the service's shared async
+    /// client, so concurrent scans overlap within its RPC limits.
+    async fn scan_first_seen(&self, mint: &str) -> Result<(i64, bool), Box<dyn std::error::Error>> {
+        let mint_pubkey = Pubkey::from_str(mint)?;
+
+        let mut oldest_time = None;
+        let mut before = None;
+        let mut complete = false;
+        for _ in 0..AGE_SCAN_MAX_PAGES {
+            let page = self
+                .rpc_client
+                .get_signatures_for_address_with_config(
+                    &mint_pubkey,
+                    GetConfirmedSignaturesForAddress2Config {
+                        before,
+                        until: None,
+                        limit: Some(SIGNATURES_PAGE_LIMIT),
+                        commitment: None,
+                    },
+                )
+                .await?;
+            let Some(oldest) = page.last() else {
+                complete = true;
+                break;
+            };
+            before = Some(Signature::from_str(&oldest.signature)?);
+            oldest_time = page
+                .iter()
+                .rev()
+                .find_map(|status| status.block_time)
+                .or(oldest_time);
+            if page.len() < SIGNATURES_PAGE_LIMIT {
+                complete = true;
+                break;
+            }
+        }
+
+        let oldest_time =
+            oldest_time.ok_or_else(|| format!("No dated transactions for {}", mint))?;
+        if complete {
+            self.db_client
+                .execute(
+                    "INSERT INTO token_first_seen (mint, first_seen) VALUES ($1, $2) 
+                     ON CONFLICT (mint) DO UPDATE SET first_seen = $2",
+                    &[&mint, &oldest_time],
+                )
+                .await?;
+        }
+        Ok((oldest_time, complete))
+    }
+
+    /// Rolls authorities, mutability, creator verification, age, price
+    /// availability and probe-order slippage into a single verdict, using the
+    /// thresholds from the `tradeability` config section.
+    pub async fn tradeability(
+        &self,
+        mint: &str,
+    ) -> Result<Tradeability, Box<dyn std::error::Error>> {
+        self.in_context("tradeability", mint, async {
+            let metadata = self.get_metadata(mint).await?;
+            let creators = self.verify_creators(mint, &[]).await?;
+
+            let price_available = match self.get_price(mint).await {
+                Ok(price) => price > 0.0,
+                Err(e) => match TokenServiceError::cause(e.as_ref()) {
+                    Some(TokenServiceError::PriceUnavailable { .. })
+                    | Some(TokenServiceError::PriceMissing { .. }) => false,
+                    _ => return Err(e),
+                },
+            };
+
+            let slippage_pct = if price_available {
+                match self
+                    .estimate_slippage(mint, self.tradeability.probe_usd)
+                    .await
+                {
+                    Ok(slippage) => Some(slippage),
+                    Err(e) => match TokenServiceError::cause(e.as_ref()) {
+                        Some(TokenServiceError::NoRoute { .. }) => None,
+                        _ => return Err(e),
+                    },
+                }
+            } else {
+                None
+            };
+
+            // A scan cut short at `AGE_SCAN_MAX_PAGES` only bounds the age from
+            // below, which would make busy, established mints look brand new.
+            let age = match self.token_age(mint).await {
+                Ok((age, true)) => Some(age),
+                Ok((_, false)) => None,
+                Err(e) => {
+                    eprintln!("Could not determine age of {}: {}", mint, e);
+                    None
+                }
+            };
+
+            let signals = TradeabilitySignals {
+                mint_authority: metadata.mint_authority.is_some(),
+                freeze_authority: metadata.freeze_authority.is_some(),
+                is_mutable: metadata.is_mutable,
+                creators_verified: (!creators.creators.is_empty())
+                    .then(|| creators.creators.iter().any(|creator| creator.verified)),
+                age,
+                price_available,
+                slippage_pct,
+            };
+            Ok(tradeability::assess(&signals, &self.tradeability))
+        })
+        .await
+    }
+
+    /// Returns `(name, symbol)` keyed by canonical mint, reading only metadata
+    /// PDAs. Cached metadata is used where present and the remaining mints are
+    /// fetched with a single `getMultipleAccounts` call per 100 mints. Mints
+    /// without a metadata account are left out of the result, as are failed
+    /// ones under `BatchMode::BestEffort`.
+    pub async fn get_names(
+        &self,
+        mints: &[&str],
+        mode: BatchMode,
+    ) -> Result<HashMap<String, (String, String)>, Box<dyn std::error::Error>> {
+        self.in_context("get_names", "", async {
+            self.ensure_metadata_enabled()?;
+            let mints = mints
+                .iter()
+                .map(|mint| canonical_mint(mint))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let mut names = HashMap::new();
+            let rows = self
+                .db_client
+                .query(
+                    "SELECT mint, metadata FROM token_metadata WHERE mint = ANY($1)",
+                    &[&mints],
+                )
+                .await?;
+            for row in rows {
+                let metadata = self.codecs.open(row.get(1))?;
+                // Same staleness rule as `get_from_cache`: rows from before
+                // a layout bump are refetched.
+                if metadata["version"].as_u64() < Some(METADATA_VERSION as u64) {
+                    continue;
+                }
+                if let (Some(name), Some(symbol)) =
+                    (metadata["name"].as_str(), metadata["symbol"].as_str())
+                {
+                    names.insert(row.get(0), (name.to_string(), symbol.to_string()));
+                }
+            }
+
+            let mut uncached: Vec<&String> = mints
+                .iter()
+                .filter(|mint| !names.contains_key(*mint))
+                .collect();
+            uncached.sort();
+            uncached.dedup();
+            let pdas = uncached
+                .iter()
+                .map(|mint| Ok(metadata_pda(&Pubkey::from_str(mint)?)))
+                .collect::<Result<Vec<_>, Box<dyn std::error::Error>>>()?;
+            let accounts = self
+                .get_multiple_decoded(&pdas, |account| Ok(account.data.clone()))
+                .await;
+            for (mint, account) in uncached.iter().zip(accounts) {
+                let decoded = account.and_then(|data| self.decode_metadata_account(mint, &data));
+                let metadata = match decoded {
+                    Ok((metadata, _)) => metadata,
+                    Err(e) => match TokenServiceError::cause(e.as_ref()) {
+                        Some(TokenServiceError::AccountNotFound { .. }) => continue,
+                        _ if mode == BatchMode::BestEffort => {
+                            eprintln!("Failed to read metadata for {}: {}", mint, e);
+                            continue;
+                        }
+                        _ => return Err(e),
+                    },
+                };
+                let (symbol, _) = self.display_symbol(mint, &metadata.symbol);
+                let (name, _) = self.display_name(sanitize::trim_padding(&metadata.name));
+                let (name, symbol, _) = self.limit_lengths(name, symbol);
+                names.insert(mint.to_string(), (name, symbol));
+            }
+
+            Ok(names)
+        })
+        .await
+    }
+
+    /// Cheap data-quality signal: whether the metadata URI and the image it
+    /// points to currently respond, checked with HEAD requests and cached
+    /// briefly.
+    pub async fn offchain_reachable(&self, mint: &str) -> Result<bool, Box<dyn std::error::Error>> {
+        self.in_context("offchain_reachable", mint, async {
+            self.ensure_metadata_enabled()?;
+            let mint = &canonical_mint(mint)?;
+            if let Some(reachable) = self.get_reachability_from_cache(mint).await? {
+                return Ok(reachable);
+            }
+
+            let uri = self.get_metadata(mint).await?.uri;
+            let mut reachable = !uri.is_empty() && self.url_reachable(&uri).await;
+            if reachable {
+                reachable = match self.get_offchain_metadata(mint).await {
+                    Ok(offchain) => match offchain.image {
+                        Some(image) => self.url_reachable(&image).await,
+                        None => true,
+                    },
+                    Err(e) => {
+                        eprintln!("Off-chain metadata for {} unavailable: {}", mint, e);
+                        false
+                    }
+                };
+            }
+
+            self.save_reachability_to_cache(mint, reachable).await?;
+            Ok(reachable)
+        })
+        .await
+    }
+
+    /// HEAD-checks the first fetchable location for `uri` (the URI itself, or
+    /// the first gateway for `ipfs://` URIs). Servers that reject HEAD get a
+    /// one-byte ranged GET instead.
+    async fn url_reachable(&self, uri: &str) -> bool {
+        let Some((_, url)) = offchain::candidate_urls(uri, &self.offchain.gateways)
+            .into_iter()
+            .next()
+        else {
+            return false;
+        };
+        let timeout = Duration::from_secs(self.offchain.timeout_secs);
+        let Ok(_permit) = self.offchain_permits.acquire().await else {
+            return false;
+        };
+
+        match self.http_client.head(&url).timeout(timeout).send().await {
+            Ok(response) if response.status().is_success() => true,
+            Ok(response) if response.status() == reqwest::StatusCode::METHOD_NOT_ALLOWED => self
+                .http_client
+                .get(&url)
+                .header(reqwest::header::RANGE, "bytes=0-0")
+                .timeout(timeout)
+                .send()
+                .await
+                .is_ok_and(|response| response.status().is_success()),
+            _ => false,
+        }
+    }
+
+    async fn get_reachability_from_cache(
+        &self,
+        mint: &str,
+    ) -> Result<Option<bool>, Box<dyn std::error::Error>> {
+        let row = self
+            .db_client
+            .query_opt(
+                "SELECT reachable FROM token_offchain_reachability 
+                 WHERE mint = $1 AND last_updated > $2",
+                &[
+                    &mint,
+                    &(std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)?
+                        .as_secs() as i64
+                        - self.reachability_cache_duration as i64),
+                ],
+            )
+            .await?;
+
+        Ok(row.map(|row| row.get(0)))
+    }
+
+    async fn save_reachability_to_cache(
+        &self,
+        mint: &str,
+        reachable: bool,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs() as i64;
+
+        self.db_client
+            .execute(
+                "INSERT INTO token_offchain_reachability (mint, reachable, last_updated) 
+                 VALUES ($1, $2, $3)
+                 ON CONFLICT (mint) DO UPDATE SET reachable = $2, last_updated = $3",
+                &[&mint, &reachable, &now],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Overhead of one DCA fill buying `usd_per_cycle` worth of the mint: the
+    /// network fees of the fill transaction plus the fees taken from its
+    /// output.
+    pub async fn estimate_cycle_cost(
+        &self,
+        mint: &str,
+        usd_per_cycle: f64,
+    ) -> Result<CycleCost, Box<dyn std::error::Error>> {
+        self.in_context("estimate_cycle_cost", mint, async {
+            if !usd_per_cycle.is_finite() || usd_per_cycle <= 0.0 {
+                return Err(format!("Invalid cycle size: {}", usd_per_cycle).into());
+            }
+            let mint = &canonical_mint(mint)?;
+            let sol_price = self.sol_usd_price().await?;
+            if !sol_price.is_finite() || sol_price <= 0.0 {
+                return Err(format!("Invalid SOL/USD price: {}", sol_price).into());
+            }
+            let price = self.get_price(mint).await?;
+
+            let mut fees: Vec<u64> = self
+                .rpc_client
+                .get_recent_prioritization_fees(&[programs::JUPITER_DCA])
+                .await?
+                .iter()
+                .map(|fee| fee.prioritization_fee)
+                .collect();
+            fees.sort_unstable();
+            let micro_lamports_per_cu = fees.get(fees.len() / 2).copied().unwrap_or_default();
+            let priority_lamports =
+                micro_lamports_per_cu.saturating_mul(DCA_FILL_COMPUTE_UNITS) / 1_000_000;
+
+            let transfer_fee_usd = match self.get_transfer_fee(mint).await? {
+                Some(fee) => {
+                    let scale = 10f64.powi(self.get_decimals(mint).await? as i32);
+                    let raw_amount = (usd_per_cycle / price * scale) as u64;
+                    fee.fee_for(raw_amount) as f64 / scale * price
+                }
+                None => 0.0,
+            };
+
+            let lamports_per_sol = solana_sdk::native_token::LAMPORTS_PER_SOL as f64;
+            let base_fee_sol = LAMPORTS_PER_SIGNATURE as f64 / lamports_per_sol;
+            let priority_fee_sol = priority_lamports as f64 / lamports_per_sol;
+            let platform_fee_usd = usd_per_cycle * DCA_PLATFORM_FEE_BPS as f64 / 10_000.0;
+            let total_usd =
+                (base_fee_sol + priority_fee_sol) * sol_price + transfer_fee_usd + platform_fee_usd;
+            Ok(CycleCost {
+                base_fee_sol,
+                priority_fee_sol,
+                transfer_fee_usd,
+                platform_fee_usd,
+                total_sol: total_usd / sol_price,
+                total_usd,
+            })
+        })
+        .await
+    }
+
+    /// The mint's Token-2022 transfer fee for the current epoch, or `None` for
+    /// classic SPL mints and Token-2022 mints without the extension.
+    pub async fn get_transfer_fee(
+        &self,
+        mint: &str,
+    ) -> Result<Option<TransferFeeInfo>, Box<dyn std::error::Error>> {
+        self.in_context("get_transfer_fee", mint, async {
+            let mint_pubkey = Pubkey::from_str(&canonical_mint(mint)?)?;
+            let mint_account = self.rpc_client.get_account(&mint_pubkey).await?;
+            if mint_account.owner != programs::TOKEN_2022 {
+                return Ok(None);
+            }
+
+            let state = StateWithExtensions::<Mint>::unpack(&mint_account.data)?;
+            let Ok(config) = state.get_extension::<TransferFeeConfig>() else {
+                return Ok(None);
+            };
+            let epoch = self.rpc_client.get_epoch_info().await?.epoch;
+            let fee = config.get_epoch_fee(epoch);
+            Ok(Some(TransferFeeInfo {
+                basis_points: fee.transfer_fee_basis_points.into(),
+                maximum_fee: fee.maximum_fee.into(),
+            }))
+        })
+        .await
+    }
+
+    /// The mint's decimals, read from the mint account alone. Decimals never
+    /// change after initialisation, so the cached value never expires.
+    pub async fn get_decimals(&self, mint: &str) -> Result<u8, Box<dyn std::error::Error>> {
+        self.in_context("get_decimals", mint, async {
+            let mint = &canonical_mint(mint)?;
+            let row = self
+                .db_client
+                .query_opt(
+                    "SELECT decimals FROM token_decimals WHERE mint = $1",
+                    &[mint],
+                )
+                .await?;
+            if let Some(row) = row {
+                return Ok(row.get::<_, i16>(0) as u8);
+            }
+
+            let mint_account = self
+                .rpc_client
+                .get_account(&Pubkey::from_str(mint)?)
+                .await?;
+            let decimals = StateWithExtensions::<Mint>::unpack(&mint_account.data)?
+                .base
+                .decimals;
+
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)?
+                .as_secs() as i64;
+            self.db_client
+                .execute(
+                    "INSERT INTO token_decimals (mint, decimals, last_updated) 
+                     VALUES ($1, $2, $3)
+                     ON CONFLICT (mint) DO UPDATE SET decimals = $2, last_updated = $3",
+                    &[mint, &(decimals as i16), &now],
+                )
+                .await?;
+            Ok(decimals)
+        })
+        .await
+    }
+
+    /// Total supply minus the balances held at `exclude`, e.g. burn,
+    /// treasury and pool addresses. Each address may be a token account of
+    /// the mint or a wallet, in which case all its token accounts for the
+    /// mint are excluded; addresses with no account hold nothing. Excluded
+    /// balances are cached briefly.
+    pub async fn circulating_supply(
+        &self,
+        mint: &str,
+        exclude: &[String],
+    ) -> Result<u64, Box<dyn std::error::Error>> {
+        self.in_context("circulating_supply", mint, async {
+            let mint = &canonical_mint(mint)?;
+            let mint_pubkey = Pubkey::from_str(mint)?;
+            let supply =
+                u64::from_str(&self.rpc_client.get_token_supply(&mint_pubkey).await?.amount)?;
+
+            let mut addresses: Vec<String> = exclude
+                .iter()
+                .map(|address| address.trim().to_string())
+                .collect();
+            addresses.sort();
+            addresses.dedup();
+
+            let mut balances = self
+                .get_excluded_balances_from_cache(mint, &addresses)
+                .await?;
+            let uncached = addresses
+                .iter()
+                .filter(|address| !balances.contains_key(*address))
+                .map(|address| Pubkey::from_str(address))
+                .collect::<Result<Vec<_>, _>>()?;
+            let decoded = self
+                .get_multiple_decoded(&uncached, |account| {
+                    Ok(token_account_balance(&account.data, &mint_pubkey))
+                })
+                .await;
+            for (address, balance) in uncached.iter().zip(decoded) {
+                let balance = match balance {
+                    Ok(Some(amount)) => amount,
+                    Ok(None) => self.owner_balance(address, &mint_pubkey).await?,
+                    Err(e) => match TokenServiceError::cause(e.as_ref()) {
+                        Some(TokenServiceError::AccountNotFound { .. }) => 0,
+                        _ => return Err(e),
+                    },
+                };
+                self.save_excluded_balance_to_cache(mint, &address.to_string(), balance)
+                    .await?;
+                balances.insert(address.to_string(), balance);
+            }
+
+            let excluded = balances
+                .values()
+                .fold(0u64, |total, balance| total.saturating_add(*balance));
+            Ok(supply.saturating_sub(excluded))
+        })
+        .await
+    }
+
+    /// Sum of the `owner` wallet's token accounts for `mint`.
+    async fn owner_balance(
+        &self,
+        owner: &Pubkey,
+        mint: &Pubkey,
+    ) -> Result<u64, Box<dyn std::error::Error>> {
+        let token_accounts = self
+            .rpc_client
+            .get_token_accounts_by_owner(owner, TokenAccountsFilter::Mint(*mint))
+            .await?
+            .iter()
+            .map(|keyed| Pubkey::from_str(&keyed.pubkey))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut total = 0u64;
+        for balance in self
+            .get_multiple_decoded(&token_accounts, |account| {
+                Ok(token_account_balance(&account.data, mint).unwrap_or_default())
+            })
+            .await
+        {
+            total = total.saturating_add(balance?);
+        }
+        Ok(total)
+    }
+
+    async fn get_excluded_balances_from_cache(
+        &self,
+        mint: &str,
+        addresses: &[String],
+    ) -> Result<HashMap<String, u64>, Box<dyn std::error::Error>> {
+        let rows = self
+            .db_client
+            .query(
+                "SELECT address, amount FROM token_excluded_balances 
+                 WHERE mint = $1 AND address = ANY($2) AND last_updated > $3",
+                &[
+                    &mint,
+                    &addresses,
+                    &(std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)?
+                        .as_secs() as i64
+                        - self.excluded_balance_cache_duration as i64),
+                ],
+            )
+            .await?;
+        rows.iter()
+            .map(|row| Ok((row.get(0), u64::from_str(row.get(1))?)))
+            .collect()
+    }
+
+    async fn save_excluded_balance_to_cache(
+        &self,
+        mint: &str,
+        address: &str,
+        amount: u64,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs() as i64;
+
+        self.db_client
+            .execute(
+                "INSERT INTO token_excluded_balances (mint, address, amount, last_updated) 
+                 VALUES ($1, $2, $3, $4)
+                 ON CONFLICT (mint, address) DO UPDATE SET amount = $3, last_updated = $4",
+                &[&mint, &address, &amount.to_string(), &now],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// USD market cap from the live token supply and the USD price.
+    pub async fn get_market_cap(&self, mint: &str) -> Result<f64, Box<dyn std::error::Error>> {
+        self.in_context("get_market_cap", mint, async {
+            let mint = &canonical_mint(mint)?;
+            let supply = self
+                .rpc_client
+                .get_token_supply(&Pubkey::from_str(mint)?)
+                .await?;
+            let price = self.get_price(mint).await?;
+            market_cap(u64::from_str(&supply.amount)?, supply.decimals, price)
+                .ok_or_else(|| format!("Market cap of {} is out of range", mint).into())
+        })
+        .await
+    }
+
+    /// Whether the account holds at least the rent-exempt minimum for its
+    /// data length; accounts below it can be reclaimed by the runtime.
+    pub async fn is_rent_exempt(&self, pubkey: &str) -> Result<bool, Box<dyn std::error::Error>> {
+        self.in_context("is_rent_exempt", pubkey, async {
+            let account = self
+                .rpc_client
+                .get_account(&Pubkey::from_str(pubkey.trim())?)
+                .await?;
+            let minimum = self
+                .rpc_client
+                .get_minimum_balance_for_rent_exemption(account.data.len())
+                .await?;
+            Ok(account.lamports >= minimum)
+        })
+        .await
+    }
+
+    /// Reverse lookup from a token account (associated or not) to its mint.
+    pub async fn mint_of_account(
+        &self,
+        token_account: &str,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        self.in_context("mint_of_account", token_account, async {
+            let account_pubkey = Pubkey::from_str(token_account.trim())?;
+            let account = self.rpc_client.get_account(&account_pubkey).await?;
+            let not_a_token_account = |reason: String| TokenServiceError::NotATokenAccount {
+                account: account_pubkey.to_string(),
+                reason,
+            };
+
+            if !programs::is_token_program(&account.owner) {
+                return Err(not_a_token_account(format!("owned by {}", account.owner)).into());
+            }
+            // Classic token accounts are exactly `Account::LEN`; Token-2022 ones
+            // may carry extensions, which the unpack below validates.
+            if account.owner == programs::SPL_TOKEN && account.data.len() != TokenAccount::LEN {
+                return Err(not_a_token_account(format!(
+                    "{} bytes of data, expected {}",
+                    account.data.len(),
+                    TokenAccount::LEN
+                ))
+                .into());
+            }
+
+            let state = StateWithExtensions::<TokenAccount>::unpack(&account.data)
+                .map_err(|e| not_a_token_account(e.to_string()))?;
+            Ok(state.base.mint.to_string())
+        })
+        .await
+    }
+
+    /// Looks up the mint's metadata (when enabled) and price, sending each
+    /// result to `sender` as it arrives so embedders decide how to present
+    /// them. Stops early, without error, once the receiver is dropped.
+    pub async fn lookup(
+        &self,
+        mint: &str,
+        sender: mpsc::Sender<LookupEvent>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.in_context("lookup", mint, async {
+            let mint = canonical_mint(mint)?;
+            if self.metadata_enabled {
+                let metadata = self.get_metadata(&mint).await?;
+                if sender
+                    .send(LookupEvent::Metadata(Box::new(metadata)))
+                    .await
+                    .is_err()
+                {
+                    return Ok(());
+                }
+            }
+            let price = self.get_price(&mint).await?;
+            let _ = sender.send(LookupEvent::Price { mint, price }).await;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Fetches metadata and price together. With `require_price`, a price
+    /// failure fails the snapshot; otherwise it comes back with `price: None`
+    /// and the error recorded in `price_error`.
+    pub async fn get_token_snapshot(
+        &self,
+        mint: &str,
+        require_price: bool,
+    ) -> Result<TokenSnapshot, Box<dyn std::error::Error>> {
+        self.in_context("get_token_snapshot", mint, async {
+            let metadata = self.get_metadata(mint).await?;
+            let (price, price_error) = match self.get_price(mint).await {
+                Ok(price) => (Some(price), None),
+                Err(e) if !require_price => {
+                    eprintln!("Snapshot price for {} failed: {}", metadata.mint, e);
+                    (None, Some(e.to_string()))
+                }
+                Err(e) => return Err(e),
+            };
+
+            Ok(TokenSnapshot {
+                metadata,
+                price,
+                price_error,
+            })
+        })
+        .await
+    }
+
+    /// The `n` largest holders of `mint` (at most the 20 that
+    /// `getTokenLargestAccounts` returns), labelling pools and burn addresses
+    /// so liquidity is not mistaken for concentration.
+    pub async fn top_holders(
+        &self,
+        mint: &str,
+        n: usize,
+    ) -> Result<Vec<HolderEntry>, Box<dyn std::error::Error>> {
+        self.in_context("top_holders", mint, async {
+            let mint_pubkey = Pubkey::from_str(&canonical_mint(mint)?)?;
+            let supply =
+                u64::from_str(&self.rpc_client.get_token_supply(&mint_pubkey).await?.amount)?;
+            let mut largest = self
+                .rpc_client
+                .get_token_largest_accounts(&mint_pubkey)
+                .await?;
+            largest.truncate(n);
+
+            let token_accounts = largest
+                .iter()
+                .map(|balance| Pubkey::from_str(&balance.address))
+                .collect::<Result<Vec<_>, _>>()?;
+            let owners = self
+                .get_multiple_decoded(&token_accounts, |account| {
+                    Ok(StateWithExtensions::<TokenAccount>::unpack(&account.data)
+                        .ok()
+                        .map(|state| state.base.owner))
+                })
+                .await
+                .into_iter()
+                .map(|owner| match owner {
+                    Ok(owner) => Ok(owner),
+                    Err(e) => match TokenServiceError::cause(e.as_ref()) {
+                        Some(TokenServiceError::AccountNotFound { .. }) => Ok(None),
+                        _ => Err(e),
+                    },
+                })
+                .collect::<Result<Vec<Option<Pubkey>>, _>>()?;
+            let known_owners: Vec<Pubkey> = owners.iter().flatten().copied().collect();
+            let mut owner_programs: HashMap<Pubkey, Pubkey> = HashMap::new();
+            let programs = self
+                .get_multiple_decoded(&known_owners, |account| Ok(account.owner))
+                .await;
+            for (owner, program) in known_owners.iter().zip(programs) {
+                match program {
+                    Ok(program) => {
+                        owner_programs.insert(*owner, program);
+                    }
+                    Err(e) => match TokenServiceError::cause(e.as_ref()) {
+                        Some(TokenServiceError::AccountNotFound { .. }) => {}
+                        _ => return Err(e),
+                    },
+                }
+            }
+
+            largest
+                .into_iter()
+                .zip(owners)
+                .map(|(balance, owner)| {
+                    let amount = u64::from_str(&balance.amount.amount)?;
+                    let label = owner
+                        .and_then(|owner| labels::holder_label(&owner, owner_programs.get(&owner)));
+                    Ok(HolderEntry {
+                        token_account: balance.address,
+                        owner: owner.map(|owner| owner.to_string()).unwrap_or_default(),
+                        amount,
+                        ui_amount: balance.amount.ui_amount_string,
+                        percentage: if supply == 0 {
+                            0.0
+                        } else {
+                            amount as f64 / supply as f64 * 100.0
+                        },
+                        label: label.map(|label| label.to_string()),
+                    })
+                })
+                .collect()
+        })
+        .await
+    }
+
+    /// USD value of a raw on-chain amount of `mint`, from cached decimals and
+    /// price where possible. Fails with `PriceUnavailable`/`PriceMissing`
+    /// when the mint has no price.
+    pub async fn amount_to_usd(
+        &self,
+        mint: &str,
+        raw_amount: u64,
+    ) -> Result<f64, Box<dyn std::error::Error>> {
+        self.in_context("amount_to_usd", mint, async {
+            let decimals = self.get_decimals(mint).await?;
+            let price = if canonical_mint(mint)? == spl_token::native_mint::id().to_string() {
+                self.sol_usd_price().await?
+            } else {
+                self.get_price(mint).await?
+            };
+            Ok(raw_amount as f64 / 10f64.powi(decimals as i32) * price)
+        })
+        .await
+    }
+
+    /// Heuristic honeypot check: whether one whole token of `mint` looks
+    /// sellable for USDC. Returns false when new token accounts start frozen
+    /// (Token-2022 `DefaultAccountState`), when one whole token is more than
+    /// a u64 amount can hold, when the transfer fee swallows the whole
+    /// amount, or when Jupiter finds no sell route. The sell is only
+    /// quoted, never signed or simulated on-chain, so restrictions enforced
+    /// at execution time (e.g. by a transfer hook) are not caught.
+    pub async fn can_sell(&self, mint: &str) -> Result<bool, Box<dyn std::error::Error>> {
+        self.in_context("can_sell", mint, async {
+            let mint = &canonical_mint(mint)?;
+            let mint_account = self
+                .rpc_client
+                .get_account(&Pubkey::from_str(mint)?)
+                .await?;
+            let state = StateWithExtensions::<Mint>::unpack(&mint_account.data)?;
+
+            if let Ok(default_state) = state.get_extension::<DefaultAccountState>() {
+                if default_state.state == AccountState::Frozen as u8 {
+                    return Ok(false);
+                }
+            }
+
+            // With 20 or more decimals one whole token does not fit in a u64,
+            // so no supply could ever hold one to sell.
+            let Some(amount) = 10u64.checked_pow(state.base.decimals as u32) else {
+                return Ok(false);
+            };
+            let amount = match self.get_transfer_fee(mint).await? {
+                Some(fee) => amount.saturating_sub(fee.fee_for(amount)),
+                None => amount,
+            };
+            if amount == 0 {
+                return Ok(false);
+            }
+
+            match self.fetch_quote_raw(mint, USDC_MINT, amount).await {
+                Ok(out_amount) => Ok(out_amount > 0),
+                Err(e) => match TokenServiceError::cause(e.as_ref()) {
+                    Some(TokenServiceError::NoRoute { .. }) => Ok(false),
+                    _ => Err(e),
+                },
+            }
+        })
+        .await
+    }
+
+    /// USD price per token a buyer actually pays for a `usd_amount` order:
+    /// spot price plus estimated slippage, spread over the tokens left after
+    /// any Token-2022 transfer fee is withheld.
+    pub async fn effective_buy_price(
+        &self,
+        mint: &str,
+        usd_amount: f64,
+    ) -> Result<f64, Box<dyn std::error::Error>> {
+        self.in_context("effective_buy_price", mint, async {
+            let spot = self.get_price(mint).await?;
+            let slippage = self.estimate_slippage(mint, usd_amount).await?;
+            let execution_price = spot * (1.0 + slippage / 100.0);
+
+            let Some(fee) = self.get_transfer_fee(mint).await? else {
+                return Ok(execution_price);
+            };
+
+            let scale = 10f64.powi(self.get_decimals(mint).await? as i32);
+            let gross_raw = (usd_amount / execution_price * scale) as u64;
+            let net_raw = gross_raw.saturating_sub(fee.fee_for(gross_raw));
+            if net_raw == 0 {
+                return Err(format!("Transfer fee consumes the whole {} order", mint).into());
+            }
+            Ok(usd_amount / (net_raw as f64 / scale))
+        })
+        .await
+    }
 }
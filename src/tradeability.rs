@@ -0,0 +1,159 @@
+use crate::config::TradeabilityConfig;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Tradeability {
+    Tradeable,
+    Caution { reasons: Vec<String> },
+    Avoid { reasons: Vec<String> },
+}
+
+/// Inputs to the verdict, gathered by `TokenService::tradeability`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TradeabilitySignals {
+    pub mint_authority: bool,
+    pub freeze_authority: bool,
+    pub is_mutable: bool,
+    /// `None` when the metadata lists no creators at all.
+    pub creators_verified: Option<bool>,
+    /// `None` when the age is unknown or only a lower bound.
+    pub age: Option<Duration>,
+    pub price_available: bool,
+    /// Slippage of the probe order, `None` when no route exists.
+    pub slippage_pct: Option<f64>,
+}
+
+pub fn assess(signals: &TradeabilitySignals, config: &TradeabilityConfig) -> Tradeability {
+    let mut avoid = Vec::new();
+    let mut caution = Vec::new();
+
+    if !signals.price_available {
+        avoid.push("No price available".to_string());
+    }
+    if signals.freeze_authority {
+        avoid.push("Freeze authority is still set".to_string());
+    }
+    if signals.mint_authority {
+        caution.push("Mint authority is still set".to_string());
+    }
+    if signals.is_mutable {
+        caution.push("Metadata is mutable".to_string());
+    }
+    if signals.creators_verified == Some(false) {
+        caution.push("No listed creator has verified the metadata".to_string());
+    }
+
+    match signals.slippage_pct {
+        None => avoid.push(format!("No swap route for a ${} order", config.probe_usd)),
+        Some(slippage) if slippage >= config.avoid_slippage_pct => avoid.push(format!(
+            "Slippage of {:.2}% on a ${} order",
+            slippage, config.probe_usd
+        )),
+        Some(slippage) if slippage >= config.caution_slippage_pct => caution.push(format!(
+            "Slippage of {:.2}% on a ${} order",
+            slippage, config.probe_usd
+        )),
+        Some(_) => {}
+    }
+
+    if let Some(age) = signals.age {
+        let hours = age.as_secs_f64() / 3600.0;
+        if age.as_secs() < config.avoid_age_secs {
+            avoid.push(format!("Token is only {:.1}h old", hours));
+        } else if age.as_secs() < config.caution_age_secs {
+            caution.push(format!("Token is only {:.1}h old", hours));
+        }
+    }
+
+    if !avoid.is_empty() {
+        avoid.extend(caution);
+        Tradeability::Avoid { reasons: avoid }
+    } else if !caution.is_empty() {
+        Tradeability::Caution { reasons: caution }
+    } else {
+        Tradeability::Tradeable
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clean() -> TradeabilitySignals {
+        TradeabilitySignals {
+            mint_authority: false,
+            freeze_authority: false,
+            is_mutable: false,
+            creators_verified: Some(true),
+            age: Some(Duration::from_secs(30 * 24 * 3600)),
+            price_available: true,
+            slippage_pct: Some(0.5),
+        }
+    }
+
+    #[test]
+    fn clean_token_is_tradeable() {
+        let config = TradeabilityConfig::default();
+        assert_eq!(assess(&clean(), &config), Tradeability::Tradeable);
+
+        let unknown_age = TradeabilitySignals {
+            age: None,
+            ..clean()
+        };
+        assert_eq!(assess(&unknown_age, &config), Tradeability::Tradeable);
+    }
+
+    #[test]
+    fn soft_signals_only_caution() {
+        let signals = TradeabilitySignals {
+            mint_authority: true,
+            slippage_pct: Some(3.0),
+            ..clean()
+        };
+        match assess(&signals, &TradeabilityConfig::default()) {
+            Tradeability::Caution { reasons } => assert_eq!(reasons.len(), 2),
+            other => panic!("expected caution, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn avoid_reasons_come_before_caution_reasons() {
+        let signals = TradeabilitySignals {
+            is_mutable: true,
+            freeze_authority: true,
+            ..clean()
+        };
+        assert_eq!(
+            assess(&signals, &TradeabilityConfig::default()),
+            Tradeability::Avoid {
+                reasons: vec![
+                    "Freeze authority is still set".to_string(),
+                    "Metadata is mutable".to_string(),
+                ]
+            }
+        );
+    }
+
+    #[test]
+    fn missing_route_and_young_age_avoid() {
+        let config = TradeabilityConfig::default();
+        let no_route = TradeabilitySignals {
+            slippage_pct: None,
+            ..clean()
+        };
+        assert!(matches!(
+            assess(&no_route, &config),
+            Tradeability::Avoid { .. }
+        ));
+
+        let young = TradeabilitySignals {
+            age: Some(Duration::from_secs(config.avoid_age_secs - 1)),
+            ..clean()
+        };
+        assert!(matches!(
+            assess(&young, &config),
+            Tradeability::Avoid { .. }
+        ));
+    }
+}